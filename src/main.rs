@@ -1,16 +1,18 @@
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Write as FmtWrite;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use calamine::{open_workbook, DataType, Reader, Xlsx};
+use base64::Engine;
+use calamine::{open_workbook_auto, DataType, Reader};
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, Weekday};
 use crossterm::{
     cursor::MoveTo,
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
@@ -25,10 +27,15 @@ use ratatui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols::Marker,
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Wrap},
+    widgets::{
+        Axis, Bar, BarChart, BarGroup, Block, Borders, Cell, Chart, Clear as ClearWidget, Dataset,
+        Gauge, GraphType, List, ListItem, ListState, Paragraph, Row, Table, Wrap,
+    },
     Frame, Terminal,
 };
+use regex::Regex;
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 
@@ -40,6 +47,172 @@ struct Config {
     bill_dir_path: String,
     #[serde(default)]
     weather_api_key: String,
+    /// 已完成 TODO 的归档文件，留空时使用 TODO 同目录下的 TODO_ARCHIVE.md
+    #[serde(default)]
+    todo_archive_file_path: String,
+    /// 番茄钟时长（分钟）
+    #[serde(default = "default_pomodoro_minutes")]
+    pomodoro_minutes: u64,
+    /// 打开链接使用的命令
+    #[serde(default = "default_url_opener")]
+    url_opener: String,
+    /// 死链检查报告路径，留空时使用资源文件同目录下的 LINK_REPORT.md
+    #[serde(default)]
+    link_report_path: String,
+    #[serde(default = "default_link_check_concurrency")]
+    link_check_concurrency: usize,
+    #[serde(default = "default_link_check_timeout_secs")]
+    link_check_timeout_secs: u64,
+    /// Cyber 页面左侧分类栏使用的列名
+    #[serde(default = "default_cyber_category_column")]
+    cyber_category_column: String,
+    /// 额外的 Markdown 表格页面，每项对应主菜单中的一个入口
+    #[serde(default)]
+    pages: Vec<PageConfig>,
+    /// 账单分类规则文件，留空时使用账单目录下的 bill_rules.toml
+    #[serde(default)]
+    bill_rules_file_path: String,
+    /// 分析账单时跳过的交易状态（包含匹配），如“交易关闭”
+    #[serde(default = "default_bill_exclude_statuses")]
+    bill_exclude_statuses: Vec<String>,
+    /// 跨平台配对时两笔交易允许相差的小时数（银行入账常有延迟）
+    #[serde(default = "default_bill_pair_window_hours")]
+    bill_pair_window_hours: i64,
+    /// 各分类每月预算（元），如 `[budgets]` 下的 `"餐饮" = 1500`（中文键需加引号）
+    #[serde(default)]
+    budgets: HashMap<String, f64>,
+    /// 导出 Beancount / hledger 分录时的账户映射
+    #[serde(default)]
+    accounts: AccountConfig,
+    /// 自定义账单格式，按列名映射任意 CSV / Excel 导出
+    #[serde(default)]
+    bill_formats: Vec<BillFormatConfig>,
+}
+
+/// `[[bill_formats]]`：按表头列名映射交易字段，优先于内置格式识别
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+struct BillFormatConfig {
+    /// 格式名称，同时作为交易来源与默认支付方式
+    name: String,
+    /// 表头及之前必须出现的文字（如银行名称），为空时只要表头包含所映射的列即可
+    detect: Vec<String>,
+    /// 日期列（可含时间），必填
+    date: String,
+    /// 单独的时间列
+    time: String,
+    partner: String,
+    product: String,
+    /// 带符号的金额列；与分开的 income / expense 二选一
+    amount: String,
+    income: String,
+    expense: String,
+    /// “收/支”方向列，设置后金额取绝对值
+    direction: String,
+    category: String,
+    payment_method: String,
+    status: String,
+    order_id: String,
+    /// 金额列中正数表示支出（信用卡账单常见），默认负数表示支出
+    positive_is_expense: bool,
+}
+
+/// `[accounts]`：支付方式、来源与分类到复式记账账户的映射
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct AccountConfig {
+    /// 支付方式（包含匹配，最长者优先）→ 账户，如 `"花呗" = "Liabilities:Huabei"`
+    payment: HashMap<String, String>,
+    /// 支付方式未命中时按来源平台，如 `"支付宝" = "Assets:Alipay"`
+    source: HashMap<String, String>,
+    /// 分类 → 支出或收入账户，如 `"餐饮" = "Expenses:Food"`
+    category: HashMap<String, String>,
+    default_asset: String,
+    default_expense: String,
+    default_income: String,
+    currency: String,
+    /// 已有的账本文件，导出时跳过其中已记录 txid 的交易
+    journals: Vec<String>,
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        Self {
+            payment: HashMap::new(),
+            source: HashMap::from([
+                ("支付宝".to_string(), "Assets:Alipay".to_string()),
+                ("微信".to_string(), "Assets:WeChat".to_string()),
+            ]),
+            category: HashMap::new(),
+            default_asset: "Assets:Unknown".into(),
+            default_expense: "Expenses:Uncategorized".into(),
+            default_income: "Income:Uncategorized".into(),
+            currency: "CNY".into(),
+            journals: Vec::new(),
+        }
+    }
+}
+
+impl AccountConfig {
+    /// 资金一侧的账户：支付方式 → 来源平台 → 默认资产账户
+    fn funding_account(&self, entry: &BillEntry) -> String {
+        self.payment
+            .iter()
+            .filter(|(key, _)| !key.is_empty() && entry.payment_method.contains(key.as_str()))
+            .max_by_key(|(key, _)| key.chars().count())
+            .or_else(|| self.source.get_key_value(&entry.source))
+            .map(|(_, account)| account.clone())
+            .unwrap_or_else(|| self.default_asset.clone())
+    }
+
+    /// 收支一侧的账户：按分类映射，未配置时按收支方向取默认账户
+    fn category_account(&self, entry: &BillEntry) -> String {
+        self.category
+            .get(&entry.category)
+            .cloned()
+            .unwrap_or_else(|| match entry.flow {
+                BillFlow::Income => self.default_income.clone(),
+                _ => self.default_expense.clone(),
+            })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct PageConfig {
+    title: String,
+    path: String,
+    /// 表格选择器："2" 表示文件中第 2 张表，其他文字匹配表格上方最近的标题
+    #[serde(default)]
+    table: Option<String>,
+}
+
+fn default_bill_pair_window_hours() -> i64 {
+    48
+}
+
+fn default_bill_exclude_statuses() -> Vec<String> {
+    // 已退款的原交易保留，由退款记录冲抵
+    vec!["交易关闭".into()]
+}
+
+fn default_cyber_category_column() -> String {
+    "分类".into()
+}
+
+fn default_link_check_concurrency() -> usize {
+    8
+}
+
+fn default_link_check_timeout_secs() -> u64 {
+    10
+}
+
+fn default_url_opener() -> String {
+    "xdg-open".into()
+}
+
+fn default_pomodoro_minutes() -> u64 {
+    25
 }
 
 impl Default for Config {
@@ -49,10 +222,53 @@ impl Default for Config {
             cyber_resource_file_path: "md/CyberResource.md".into(),
             bill_dir_path: "tmp".into(),
             weather_api_key: String::new(),
+            todo_archive_file_path: String::new(),
+            pomodoro_minutes: default_pomodoro_minutes(),
+            url_opener: default_url_opener(),
+            link_report_path: String::new(),
+            link_check_concurrency: default_link_check_concurrency(),
+            link_check_timeout_secs: default_link_check_timeout_secs(),
+            cyber_category_column: default_cyber_category_column(),
+            pages: Vec::new(),
+            bill_rules_file_path: String::new(),
+            bill_exclude_statuses: default_bill_exclude_statuses(),
+            bill_pair_window_hours: default_bill_pair_window_hours(),
+            budgets: HashMap::new(),
+            accounts: AccountConfig::default(),
+            bill_formats: Vec::new(),
         }
     }
 }
 
+impl Config {
+    fn todo_archive_path(&self) -> PathBuf {
+        if !self.todo_archive_file_path.trim().is_empty() {
+            return PathBuf::from(self.todo_archive_file_path.trim());
+        }
+        Path::new(&self.todo_file_path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join("TODO_ARCHIVE.md")
+    }
+
+    fn link_report_file(&self) -> PathBuf {
+        if !self.link_report_path.trim().is_empty() {
+            return PathBuf::from(self.link_report_path.trim());
+        }
+        Path::new(&self.cyber_resource_file_path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join("LINK_REPORT.md")
+    }
+
+    fn bill_rules_file(&self) -> PathBuf {
+        if !self.bill_rules_file_path.trim().is_empty() {
+            return PathBuf::from(self.bill_rules_file_path.trim());
+        }
+        Path::new(&self.bill_dir_path).join("bill_rules.toml")
+    }
+}
+
 fn load_config() -> Config {
     // 编译期嵌入 config.toml，二进制不再依赖外部文件
     let embedded = include_str!("../config.toml");
@@ -94,6 +310,7 @@ enum WeatherCard {
 #[derive(Debug, Clone)]
 struct DailySummary {
     day_label: String,
+    date: Option<NaiveDate>,
     condition: String,
     temperature: String,
 }
@@ -116,6 +333,8 @@ struct SeniverseLocation {
 
 #[derive(Debug, Deserialize)]
 struct SeniverseDaily {
+    #[serde(default)]
+    date: String,
     #[serde(default)]
     text_day: String,
     #[serde(default)]
@@ -150,10 +369,7 @@ fn fetch_weather_board(cfg: Config, sender: mpsc::Sender<Vec<WeatherCard>>) {
             return;
         }
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(5))
-            .build()
-            .unwrap_or_else(|_| Client::new());
+        let client = build_http_client(Duration::from_secs(5));
 
         // 使用并行请求提高性能
         let client = Arc::new(client);
@@ -164,7 +380,7 @@ fn fetch_weather_board(cfg: Config, sender: mpsc::Sender<Vec<WeatherCard>>) {
             .map(|loc| {
                 let client = Arc::clone(&client);
                 let key = Arc::clone(&key);
-                let loc = loc.clone();
+                let loc = *loc;
 
                 std::thread::spawn(move || fetch_city_weather(&client, &key, &loc))
             })
@@ -177,6 +393,31 @@ fn fetch_weather_board(cfg: Config, sender: mpsc::Sender<Vec<WeatherCard>>) {
     });
 }
 
+fn build_http_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .user_agent(concat!("Entry/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .unwrap_or_else(|_| Client::new())
+}
+
+const WEEKDAYS: [&str; 7] = ["周一", "周二", "周三", "周四", "周五", "周六", "周日"];
+
+fn weekday_label(day: NaiveDate) -> &'static str {
+    WEEKDAYS[day.weekday().num_days_from_monday() as usize]
+}
+
+/// 预报日期的显示名：近三天用今天/明天/后天，之后用“月-日 周几”
+fn forecast_day_label(day: NaiveDate, today: NaiveDate) -> String {
+    match (day - today).num_days() {
+        0 => "今天".into(),
+        1 => "明天".into(),
+        2 => "后天".into(),
+        _ => format!("{} {}", day.format("%m-%d"), weekday_label(day)),
+    }
+}
+
 fn fetch_city_weather(client: &Client, api_key: &str, loc: &WeatherLocation) -> WeatherCard {
     let response = match client
         .get(WEATHER_ENDPOINT)
@@ -186,7 +427,8 @@ fn fetch_city_weather(client: &Client, api_key: &str, loc: &WeatherLocation) ->
             ("language", "zh-Hans"),
             ("unit", "c"),
             ("start", "0"),
-            ("days", "3"),
+            // 按议程天数请求，实际返回天数受套餐限制
+            ("days", &AGENDA_DAYS.to_string()),
         ])
         .send()
     {
@@ -264,12 +506,10 @@ fn fetch_city_weather(client: &Client, api_key: &str, loc: &WeatherLocation) ->
             let result = data.results.first();
             match result {
                 Some(res) => {
-                    let labels = ["今天", "明天", "后天"];
                     let forecasts: Vec<DailySummary> = res
                         .daily
                         .iter()
-                        .zip(labels.iter())
-                        .map(|(day, label)| {
+                        .map(|day| {
                             let condition = if day.text_day.is_empty() {
                                 "未知天气".to_string()
                             } else {
@@ -285,8 +525,12 @@ fn fetch_city_weather(client: &Client, api_key: &str, loc: &WeatherLocation) ->
                             } else {
                                 day.low.clone()
                             };
+                            let date = parse_date(&day.date);
                             DailySummary {
-                                day_label: (*label).to_string(),
+                                day_label: date
+                                    .map(|d| forecast_day_label(d, today()))
+                                    .unwrap_or_else(|| day.date.clone()),
+                                date,
                                 condition,
                                 temperature: format!("{low}C~{high}C"),
                             }
@@ -323,396 +567,4073 @@ fn read_plain(path: &str) -> Result<String, Box<dyn std::error::Error>> {
     Ok(fs::read_to_string(path)?)
 }
 
-fn read_todo(cfg: &Config) -> Result<String, Box<dyn std::error::Error>> {
-    read_plain(&cfg.todo_file_path)
-}
-
 fn read_cyber(cfg: &Config) -> Result<String, Box<dyn std::error::Error>> {
     read_plain(&cfg.cyber_resource_file_path)
 }
 
 // ---------------- Parsing ----------------
 fn parse_table(content: &str, min_cols: usize) -> Vec<Vec<String>> {
+    table_lines(content, min_cols)
+        .into_iter()
+        .map(|(_, cells)| cells)
+        .collect()
+}
+
+/// 与 parse_table 相同的解析规则，额外返回每行所在的行号，便于写回文件
+fn table_lines(content: &str, min_cols: usize) -> Vec<(usize, Vec<String>)> {
     let mut out = Vec::new();
-    for line in content.lines() {
+    for (idx, line) in content.lines().enumerate() {
         let t = line.trim();
-        if t.starts_with('|') && t.ends_with('|') {
-            let cells: Vec<String> = t
+        if t.len() >= 2 && t.starts_with('|') && t.ends_with('|') {
+            // 保留中间的空单元格，否则列会错位
+            let cells: Vec<String> = t[1..t.len() - 1]
                 .split('|')
                 .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
                 .collect();
-            if cells.len() >= min_cols {
-                out.push(cells);
+            if cells.iter().filter(|c| !c.is_empty()).count() >= min_cols {
+                out.push((idx, cells));
             }
         }
     }
     out
 }
 
-type TableLoader = fn(&Config) -> Result<String, Box<dyn std::error::Error>>;
-const SCROLL_WINDOW: usize = 10;
+fn format_table_row(cells: &[String]) -> String {
+    format!("| {} |", cells.join(" | "))
+}
 
-fn load_table(rows: &mut Vec<Vec<String>>, scroll: &mut usize, loader: TableLoader, cfg: &Config) {
-    match loader(cfg) {
-        Ok(s) => {
-            *rows = parse_table(&s, 1);
-        }
-        Err(_) => rows.clear(),
-    }
-    *scroll = 0;
+fn is_separator_row(row: &[String]) -> bool {
+    !row.is_empty()
+        && row
+            .iter()
+            .all(|c| !c.is_empty() && c.chars().all(|ch| ch == '-' || ch == ':'))
 }
 
-fn edit_table(
-    rows: &mut Vec<Vec<String>>,
-    scroll: &mut usize,
-    loader: TableLoader,
-    path: &str,
-    cfg: &Config,
-    force_redraw: &mut bool,
-) {
-    if open_in_neovim(path).is_ok() {
-        load_table(rows, scroll, loader, cfg);
-    } else {
-        rows.clear();
-        *scroll = 0;
-    }
-    *force_redraw = true;
+fn find_column(header: &[String], names: &[&str]) -> Option<usize> {
+    header.iter().position(|h| {
+        let h = h.trim().to_lowercase();
+        names.iter().any(|n| h == *n)
+    })
 }
 
-fn scroll_up(scroll: &mut usize) {
-    if *scroll > 0 {
-        *scroll -= 1;
+/// 按行号修改 Markdown 文件：先替换，再按行号从大到小插入
+fn rewrite_lines(
+    path: &str,
+    replace: &[(usize, String)],
+    insert_after: &[(usize, String)],
+) -> io::Result<()> {
+    let content = fs::read_to_string(path)?;
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+    for (idx, text) in replace {
+        if let Some(line) = lines.get_mut(*idx) {
+            *line = text.clone();
+        }
+    }
+    let mut inserts: Vec<&(usize, String)> = insert_after.iter().collect();
+    inserts.sort_by(|a, b| b.0.cmp(&a.0));
+    for (idx, text) in inserts {
+        let pos = (*idx + 1).min(lines.len());
+        lines.insert(pos, text.clone());
     }
+    let mut out = lines.join("\n");
+    if content.ends_with('\n') {
+        out.push('\n');
+    }
+    fs::write(path, out)
 }
 
-fn scroll_down(scroll: &mut usize, len: usize) {
-    if *scroll + SCROLL_WINDOW < len {
-        *scroll += 1;
+/// 按选择器挑出文件中的一张表：数字表示第几张表（从 1 开始），其他文字匹配表格上方最近的标题
+fn select_table(content: &str, selector: Option<&str>) -> Vec<Vec<String>> {
+    let Some(selector) = selector.map(str::trim).filter(|s| !s.is_empty()) else {
+        return parse_table(content, 1);
+    };
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut tables: Vec<(String, Vec<Vec<String>>)> = Vec::new();
+    let mut last_idx: Option<usize> = None;
+    for (idx, row) in table_lines(content, 1) {
+        if last_idx.is_none_or(|last| last + 1 != idx) {
+            let heading = lines[..idx]
+                .iter()
+                .rev()
+                .find(|l| l.trim_start().starts_with('#'))
+                .map(|l| l.trim_start().trim_start_matches('#').trim().to_string())
+                .unwrap_or_default();
+            tables.push((heading, Vec::new()));
+        }
+        if let Some((_, rows)) = tables.last_mut() {
+            rows.push(row);
+        }
+        last_idx = Some(idx);
     }
+
+    let found = match selector.parse::<usize>() {
+        Ok(n) => tables.into_iter().nth(n.saturating_sub(1)),
+        Err(_) => tables.into_iter().find(|(heading, _)| heading == selector),
+    };
+    found.map(|(_, rows)| rows).unwrap_or_default()
 }
 
-// ---------------- Bill analysis ----------------
-#[derive(Debug, Clone)]
-struct BillEntry {
-    partner: String,
-    product: String,
-    amount: f64,
+const SCROLL_WINDOW: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableKey {
+    Ignored,
+    Handled,
+    Back,
 }
 
-#[derive(Debug, Clone, Default)]
-struct BillAggregate {
-    incomes: Vec<BillEntry>,
-    expenses: Vec<BillEntry>,
+/// 表格页面的通用状态，TODO、Cyber 与 `[[pages]]` 中的页面共用
+#[derive(Debug, Default)]
+struct TablePage {
+    path: String,
+    selector: Option<String>,
+    rows: Vec<Vec<String>>,
+    scroll: usize,
+    selected: usize,
 }
 
-impl BillAggregate {
-    fn from_entries(expenses: Vec<BillEntry>, incomes: Vec<BillEntry>) -> Self {
-        Self { incomes, expenses }
+impl TablePage {
+    fn new(path: &str, selector: Option<&str>) -> Self {
+        Self {
+            path: path.to_string(),
+            selector: selector.map(|s| s.to_string()),
+            ..Self::default()
+        }
     }
 
-    fn extend(&mut self, mut other: BillAggregate) {
-        self.incomes.append(&mut other.incomes);
-        self.expenses.append(&mut other.expenses);
+    fn load(&mut self) {
+        match read_plain(&self.path) {
+            Ok(s) => self.rows = select_table(&s, self.selector.as_deref()),
+            Err(_) => self.rows.clear(),
+        }
+        self.scroll = 0;
+        // 文件可能变短，选中行不能越界
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
     }
 
-    fn is_empty(&self) -> bool {
-        self.incomes.is_empty() && self.expenses.is_empty()
+    fn edit(&mut self, force_redraw: &mut bool) {
+        if open_in_neovim(&self.path).is_ok() {
+            self.load();
+        } else {
+            self.rows.clear();
+            self.scroll = 0;
+        }
+        *force_redraw = true;
     }
 
-    fn total_income(&self) -> f64 {
-        self.incomes.iter().map(|e| e.amount).sum()
+    fn reset(&mut self) {
+        self.scroll = 0;
+        self.selected = 0;
     }
 
-    fn total_expense(&self) -> f64 {
-        self.expenses.iter().map(|e| e.amount).sum()
+    fn select_up(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        }
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        }
     }
 
-    fn net(&self) -> f64 {
-        self.total_income() - self.total_expense()
+    fn select_down(&mut self, len: usize) {
+        if self.selected + 1 < len {
+            self.selected += 1;
+        }
+        if self.selected >= self.scroll + SCROLL_WINDOW {
+            self.scroll = self.selected + 1 - SCROLL_WINDOW;
+        }
     }
 
-    fn to_markdown(&self) -> String {
-        let mut expenses_sorted = self.expenses.clone();
-        expenses_sorted.sort_by(|a, b| b.amount.partial_cmp(&a.amount).unwrap_or(Ordering::Equal));
-
-        let mut incomes_sorted = self.incomes.clone();
-        incomes_sorted.sort_by(|a, b| b.amount.partial_cmp(&a.amount).unwrap_or(Ordering::Equal));
-
-        let mut out = String::new();
-        let _ = writeln!(&mut out, "# 账单分析\n");
-
-        let _ = writeln!(out, "## 支出");
-        if expenses_sorted.is_empty() {
-            out.push_str("无支出记录。\n");
-        } else {
-            out.push_str("| 交易对方 | 商品 | 金额(元) |\n|----------|------|----------|\n");
-            for e in &expenses_sorted {
-                let _ = writeln!(out, "| {} | {} | {:.2} |", e.partner, e.product, e.amount);
+    /// 各表格页面共有的按键；`len` 为当前可见行数
+    fn handle_key(&mut self, code: KeyCode, len: usize, force_redraw: &mut bool) -> TableKey {
+        match code {
+            KeyCode::Char('q') => {
+                self.reset();
+                return TableKey::Back;
             }
-            let _ = writeln!(out, "\n总支出：{:.2} 元\n", self.total_expense());
+            KeyCode::Char('e') => self.edit(force_redraw),
+            KeyCode::Char('r') => self.load(),
+            KeyCode::Char('k') => self.select_up(),
+            KeyCode::Char('j') => self.select_down(len),
+            _ => return TableKey::Ignored,
         }
+        TableKey::Handled
+    }
+}
 
-        let _ = writeln!(out, "## 收入");
-        if incomes_sorted.is_empty() {
-            out.push_str("无收入记录。\n");
-        } else {
-            out.push_str("| 交易对方 | 商品 | 金额(元) |\n|----------|------|----------|\n");
-            for e in &incomes_sorted {
-                let _ = writeln!(out, "| {} | {} | {:.2} |", e.partner, e.product, e.amount);
-            }
-            let _ = writeln!(out, "\n总收入：{:.2} 元\n", self.total_income());
-        }
+// ---------------- TODO ----------------
+const TODO_STATUS_HEADERS: &[&str] = &["状态", "完成", "status", "done"];
+const TODO_DUE_HEADERS: &[&str] = &["截止", "截止日期", "日期", "due", "date"];
+const TODO_RECUR_HEADERS: &[&str] = &["重复", "recurrence", "repeat", "recur"];
+const DATE_FMT: &str = "%Y-%m-%d";
 
-        let net = self.net();
-        let label = if net >= 0.0 { "净收入" } else { "净支出" };
-        let _ = writeln!(out, "{}：{:.2} 元", label, net.abs());
+fn is_done_mark(cell: &str) -> bool {
+    matches!(
+        cell.trim().to_lowercase().as_str(),
+        "x" | "[x]" | "done" | "✓" | "✔" | "✅" | "完成" | "已完成"
+    )
+}
 
-        out
+fn done_mark_for(cell: &str) -> &'static str {
+    // 沿用原有的复选框写法
+    if cell.trim() == "[ ]" {
+        "[x]"
+    } else {
+        "已完成"
     }
 }
 
-struct BillState {
-    bill_dir: PathBuf,
-    files: Vec<PathBuf>,
-    processed: HashSet<PathBuf>,
-    aggregate: BillAggregate,
+fn today() -> NaiveDate {
+    Local::now().date_naive()
 }
 
-impl BillState {
-    fn new(cfg: &Config) -> Self {
-        let dir = PathBuf::from(&cfg.bill_dir_path);
-        Self {
-            bill_dir: dir,
-            files: Vec::new(),
-            processed: HashSet::new(),
-            aggregate: BillAggregate::default(),
-        }
-    }
-
-    fn refresh_files(&mut self) -> io::Result<()> {
-        if !self.bill_dir.exists() {
-            fs::create_dir_all(&self.bill_dir)?;
-        }
+fn parse_date(raw: &str) -> Option<NaiveDate> {
+    let raw = raw.trim();
+    NaiveDate::parse_from_str(raw, DATE_FMT)
+        .or_else(|_| NaiveDate::parse_from_str(raw, "%Y/%m/%d"))
+        .ok()
+}
 
-        let mut list: Vec<PathBuf> = fs::read_dir(&self.bill_dir)?
-            .filter_map(|entry| entry.ok())
-            .map(|entry| entry.path())
-            .filter(|path| {
-                path.extension()
-                    .and_then(|ext| ext.to_str())
-                    .map(|ext| {
-                        let ext_lc = ext.to_ascii_lowercase();
-                        ext_lc == "xlsx" || ext_lc == "csv"
-                    })
-                    .unwrap_or(false)
-            })
-            .collect();
-        list.sort();
-        self.files = list;
-        Ok(())
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recurrence {
+    Days(u32),
+    Weekday(Weekday),
+    MonthDay(u32),
+}
 
-    fn pending_count(&self) -> usize {
-        self.files
-            .iter()
-            .filter(|p| !self.processed.contains(*p))
-            .count()
+impl Recurrence {
+    /// 支持 `every day`、`every 3 days`、`every week`、`every mon`、`monthly 1st` 等写法
+    fn parse(raw: &str) -> Option<Self> {
+        let text = raw.trim().to_lowercase();
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.as_slice() {
+            ["daily"] | ["every", "day"] | ["每天"] => Some(Recurrence::Days(1)),
+            ["weekly"] | ["every", "week"] | ["每周"] => Some(Recurrence::Days(7)),
+            ["every", n, "days"] => n.parse().ok().filter(|n| *n > 0).map(Recurrence::Days),
+            ["every", n, "weeks"] => n
+                .parse::<u32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .map(|n| Recurrence::Days(n * 7)),
+            ["every", day] => day.parse::<Weekday>().ok().map(Recurrence::Weekday),
+            ["monthly", day] | ["every", "month", day] => {
+                let digits: String = day.chars().take_while(|c| c.is_ascii_digit()).collect();
+                digits
+                    .parse()
+                    .ok()
+                    .filter(|d| (1..=31).contains(d))
+                    .map(Recurrence::MonthDay)
+            }
+            _ => None,
+        }
     }
 
-    fn analyze_pending(&mut self) -> Result<usize, String> {
-        let mut success = 0usize;
-        for path in &self.files {
-            if self.processed.contains(path) {
-                continue;
+    /// 从原截止日期（或今天）推算下一次截止日期，保证结果晚于今天
+    fn next_due(&self, due: Option<NaiveDate>, today: NaiveDate) -> NaiveDate {
+        let anchor = due.map(|d| d.max(today)).unwrap_or(today);
+        match *self {
+            Recurrence::Days(n) => {
+                let mut next = due.unwrap_or(today) + ChronoDuration::days(n as i64);
+                while next <= today {
+                    next += ChronoDuration::days(n as i64);
+                }
+                next
             }
-            match analyze_bill_file(path) {
-                Ok(report) => {
-                    self.processed.insert(path.clone());
-                    self.aggregate.extend(report);
-                    success += 1;
+            Recurrence::Weekday(wd) => {
+                let mut next = anchor + ChronoDuration::days(1);
+                while next.weekday() != wd {
+                    next += ChronoDuration::days(1);
                 }
-                Err(_) => return Err("分析失败".into()),
+                next
+            }
+            Recurrence::MonthDay(day) => {
+                let mut next = anchor + ChronoDuration::days(1);
+                while next.day() != day.min(days_in_month(next.year(), next.month())) {
+                    next += ChronoDuration::days(1);
+                }
+                next
             }
         }
-        if success == 0 {
-            return Err("没有需要分析的账单".into());
-        }
-        Ok(success)
-    }
-
-    fn export_reports(&self, target_dir: &Path) -> io::Result<usize> {
-        if self.aggregate.is_empty() {
-            return Ok(0);
-        }
-        fs::create_dir_all(target_dir)?;
-        let path = target_dir.join("bill_summary.md");
-        fs::write(path, self.aggregate.to_markdown())?;
-        Ok(1)
     }
 }
 
-fn cell_to_string(cell: &DataType) -> String {
-    match cell {
-        DataType::String(s) => s.clone(),
-        DataType::Float(f) => format!("{:.2}", f),
-        DataType::Int(i) => i.to_string(),
-        DataType::Bool(b) => b.to_string(),
-        _ => String::new(),
-    }
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (y, m) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(y, m, 1)
+        .and_then(|d| d.pred_opt())
+        .map(|d| d.day())
+        .unwrap_or(28)
 }
 
-fn parse_amount(raw: &str) -> Option<f64> {
-    let mut buf = String::new();
-    for ch in raw.chars() {
-        if ch.is_ascii_digit() || ch == '.' || ch == '-' {
-            buf.push(ch);
-        }
+/// 将第 `row_idx` 行标记为完成；若该行设置了重复规则，则在其后写入一条新的待办
+fn complete_todo(path: &str, row_idx: usize) -> Result<String, String> {
+    let content = fs::read_to_string(path).map_err(|_| "文件不存在".to_string())?;
+    let lines = table_lines(&content, 1);
+    let header = &lines.first().ok_or_else(|| "未找到表格".to_string())?.1;
+    let status_col = find_column(header, TODO_STATUS_HEADERS).ok_or("缺少状态列")?;
+
+    let (line_idx, row) = lines.get(row_idx).ok_or("请选择任务行")?;
+    if row_idx == 0 || is_separator_row(row) {
+        return Err("请选择任务行".into());
     }
-    if buf.is_empty() {
-        None
-    } else {
-        buf.parse().ok()
+    let status = row.get(status_col).cloned().unwrap_or_default();
+    if is_done_mark(&status) {
+        return Err("该任务已完成".into());
     }
-}
 
-fn analyze_bill_file(path: &Path) -> Result<BillAggregate, String> {
-    let ext = path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|s| s.to_ascii_lowercase())
-        .ok_or_else(|| "无法识别文件类型".to_string())?;
+    let mut done = row.clone();
+    done.resize(header.len().max(done.len()), String::new());
+    done[status_col] = done_mark_for(&status).to_string();
+
+    let recur_cell = find_column(header, TODO_RECUR_HEADERS)
+        .and_then(|c| row.get(c))
+        .map(|r| r.trim())
+        .filter(|r| !matches!(*r, "" | "-" | "/"));
+    let recurrence = match recur_cell {
+        Some(raw) => {
+            Some(Recurrence::parse(raw).ok_or_else(|| format!("无法识别的重复规则: {}", raw))?)
+        }
+        None => None,
+    };
+    let Some(recurrence) = recurrence else {
+        rewrite_lines(path, &[(*line_idx, format_table_row(&done))], &[])
+            .map_err(|e| format!("写入失败: {}", e))?;
+        return Ok("已完成".into());
+    };
 
-    match ext.as_str() {
-        "xlsx" => parse_wechat_bill(path),
-        "csv" => parse_alipay_bill(path),
-        _ => Err("不支持的账单格式".to_string()),
+    let due_col = find_column(header, TODO_DUE_HEADERS).ok_or("重复任务缺少截止日期列")?;
+    let due = row.get(due_col).and_then(|d| parse_date(d));
+    let next = recurrence.next_due(due, today());
+    let mut fresh = done.clone();
+    fresh[status_col] = status;
+    fresh[due_col] = next.format(DATE_FMT).to_string();
+    // 新一轮从零计时，耗时留在已完成的那一行
+    if let Some(spent_col) = find_column(header, TODO_SPENT_HEADERS) {
+        fresh[spent_col].clear();
     }
+
+    rewrite_lines(
+        path,
+        &[(*line_idx, format_table_row(&done))],
+        &[(*line_idx, format_table_row(&fresh))],
+    )
+    .map_err(|e| format!("写入失败: {}", e))?;
+    Ok(format!("已完成，下次截止：{}", next.format(DATE_FMT)))
 }
 
-fn parse_wechat_bill(path: &Path) -> Result<BillAggregate, String> {
-    let mut workbook: Xlsx<_> = open_workbook(path).map_err(|_| "无法打开文件".to_string())?;
-    let range = workbook
-        .worksheet_range_at(0)
-        .ok_or_else(|| "账单缺少工作表".to_string())
-        .and_then(|r| r.map_err(|_| "读取工作表失败".to_string()))?;
+/// 首个单元格开头的 `>` 个数表示层级，如 `> 子任务`、`>> 孙任务`
+fn todo_depth(row: &[String]) -> usize {
+    row.first()
+        .map(|c| c.chars().take_while(|ch| *ch == '>').count())
+        .unwrap_or(0)
+}
 
-    let header_idx = range
-        .rows()
-        .enumerate()
-        .find(|(_, row)| row.first().map(cell_to_string).unwrap_or_default().trim() == "交易时间")
-        .map(|(idx, _)| idx)
-        .ok_or_else(|| "未找到账单列表".to_string())?;
+fn todo_title(row: &[String]) -> String {
+    row.first()
+        .map(|c| c.trim_start_matches('>').trim().to_string())
+        .unwrap_or_default()
+}
 
-    let mut expenses = Vec::new();
-    let mut incomes = Vec::new();
+fn is_todo_row(rows: &[Vec<String>], idx: usize) -> bool {
+    idx > 0 && rows.get(idx).is_some_and(|r| !is_separator_row(r))
+}
 
-    for row in range.rows().skip(header_idx + 1) {
-        if row.iter().all(|c| matches!(c, DataType::Empty)) {
+/// 返回 `idx` 的直接子任务
+fn todo_children(rows: &[Vec<String>], idx: usize) -> Vec<usize> {
+    let depth = todo_depth(&rows[idx]);
+    let mut out = Vec::new();
+    for (i, row) in rows.iter().enumerate().skip(idx + 1) {
+        if !is_todo_row(rows, i) {
             continue;
         }
-        let category = row.get(4).map(cell_to_string).unwrap_or_default();
-        let partner = row.get(2).map(cell_to_string).unwrap_or_default();
-        let product = row.get(3).map(cell_to_string).unwrap_or_default();
-        let amount_str = row.get(5).map(cell_to_string).unwrap_or_default();
-
-        if category.is_empty() || amount_str.is_empty() {
-            continue;
+        let d = todo_depth(row);
+        if d <= depth {
+            break;
         }
-
-        let amount = match parse_amount(&amount_str) {
-            Some(v) => v,
-            None => continue,
-        };
-
-        let entry = BillEntry {
-            partner,
-            product,
-            amount,
-        };
-
-        if category.contains('支') {
-            expenses.push(entry);
-        } else if category.contains('收') {
-            incomes.push(entry);
+        if d == depth + 1 {
+            out.push(i);
         }
     }
+    out
+}
 
-    Ok(BillAggregate::from_entries(expenses, incomes))
+/// 折叠后仍可见的行（按原始行号）
+fn todo_visible_rows(rows: &[Vec<String>], folded: &HashSet<String>) -> Vec<usize> {
+    let mut out = Vec::new();
+    let mut hidden_below: Option<usize> = None;
+    for (i, row) in rows.iter().enumerate() {
+        if is_todo_row(rows, i) {
+            let depth = todo_depth(row);
+            match hidden_below {
+                Some(d) if depth > d => continue,
+                _ => hidden_below = None,
+            }
+            if folded.contains(&todo_title(row)) && !todo_children(rows, i).is_empty() {
+                hidden_below = Some(depth);
+            }
+        }
+        out.push(i);
+    }
+    out
 }
 
-fn parse_alipay_bill(path: &Path) -> Result<BillAggregate, String> {
-    let mut content = fs::read_to_string(path).map_err(|_| "无法读取文件".to_string())?;
-    if let Some(stripped) = content.strip_prefix('\u{feff}') {
-        content = stripped.to_string();
+/// `za` 等折叠操作作用的行：自身有子任务时为自身，否则为其父任务
+fn todo_fold_target(rows: &[Vec<String>], idx: usize) -> Option<usize> {
+    if !is_todo_row(rows, idx) {
+        return None;
     }
+    if !todo_children(rows, idx).is_empty() {
+        return Some(idx);
+    }
+    let depth = todo_depth(&rows[idx]);
+    (1..idx)
+        .rev()
+        .find(|&i| is_todo_row(rows, i) && todo_depth(&rows[i]) < depth)
+}
 
-    let start = content
-        .find("交易时间,")
-        .ok_or_else(|| "未找到账单数据表头".to_string())?;
-    let data = &content[start..];
-    let mut reader = ReaderBuilder::new()
-        .has_headers(true)
-        .from_reader(data.as_bytes());
+fn progress_bar(done: usize, total: usize) -> String {
+    const WIDTH: usize = 5;
+    let filled = (done * WIDTH).checked_div(total).unwrap_or(0);
+    format!(
+        "{}{} {}/{}",
+        "█".repeat(filled),
+        "░".repeat(WIDTH - filled),
+        done,
+        total
+    )
+}
 
-    let mut expenses = Vec::new();
-    let mut incomes = Vec::new();
+/// 生成树形展示用的行：缩进标题、折叠标记，并在末尾追加子任务进度列
+fn todo_display_rows(
+    rows: &[Vec<String>],
+    visible: &[usize],
+    folded: &HashSet<String>,
+) -> Vec<Vec<String>> {
+    let status_col = rows
+        .first()
+        .and_then(|h| find_column(h, TODO_STATUS_HEADERS));
+    let has_tree = (0..rows.len()).any(|i| is_todo_row(rows, i) && todo_depth(&rows[i]) > 0);
+
+    visible
+        .iter()
+        .map(|&i| {
+            let mut row = rows[i].clone();
+            if !has_tree {
+                return row;
+            }
+            if i == 0 {
+                row.push("进度".into());
+            } else if is_separator_row(&row) {
+                row.push("---".into());
+            } else {
+                let children = todo_children(rows, i);
+                let marker = if children.is_empty() {
+                    "  "
+                } else if folded.contains(&todo_title(&rows[i])) {
+                    "▸ "
+                } else {
+                    "▾ "
+                };
+                if let Some(first) = row.first_mut() {
+                    *first = format!(
+                        "{}{}{}",
+                        "  ".repeat(todo_depth(&rows[i])),
+                        marker,
+                        todo_title(&rows[i])
+                    );
+                }
+                let progress = if children.is_empty() {
+                    String::new()
+                } else {
+                    let done = children
+                        .iter()
+                        .filter(|&&c| {
+                            status_col
+                                .and_then(|col| rows[c].get(col))
+                                .is_some_and(|s| is_done_mark(s))
+                        })
+                        .count();
+                    progress_bar(done, children.len())
+                };
+                row.push(progress);
+            }
+            row
+        })
+        .collect()
+}
+
+/// 将已完成的任务移动到归档文件，按归档日期分组在 `## YYYY-MM-DD` 标题下
+fn archive_done_todos(path: &str, archive: &Path) -> Result<usize, String> {
+    let content = fs::read_to_string(path).map_err(|_| "文件不存在".to_string())?;
+    let lines = table_lines(&content, 1);
+    let header = &lines.first().ok_or_else(|| "未找到表格".to_string())?.1;
+    let status_col = find_column(header, TODO_STATUS_HEADERS).ok_or("缺少状态列")?;
+    let separator = lines
+        .get(1)
+        .filter(|(_, row)| is_separator_row(row))
+        .map(|(_, row)| format_table_row(row))
+        .unwrap_or_else(|| format_table_row(&vec!["---".to_string(); header.len()]));
+
+    let done: Vec<&(usize, Vec<String>)> = lines
+        .iter()
+        .skip(1)
+        .filter(|(_, row)| {
+            !is_separator_row(row) && row.get(status_col).is_some_and(|s| is_done_mark(s))
+        })
+        .collect();
+    if done.is_empty() {
+        return Err("没有已完成的任务".into());
+    }
+
+    let mut archived = fs::read_to_string(archive).unwrap_or_default();
+    let heading = format!("## {}", today().format(DATE_FMT));
+    let same_day = archived
+        .lines()
+        .rev()
+        .find(|l| l.starts_with("## "))
+        .is_some_and(|l| l.trim() == heading);
+    let trimmed_len = archived.trim_end().len();
+    archived.truncate(trimmed_len);
+    if !same_day {
+        if !archived.is_empty() {
+            archived.push_str("\n\n");
+        }
+        let _ = write!(
+            archived,
+            "{}\n\n{}\n{}",
+            heading,
+            format_table_row(header),
+            separator
+        );
+    }
+    for (_, row) in &done {
+        let _ = write!(archived, "\n{}", format_table_row(row));
+    }
+    archived.push('\n');
+
+    if let Some(dir) = archive.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("归档失败: {}", e))?;
+    }
+    fs::write(archive, archived).map_err(|e| format!("归档失败: {}", e))?;
+
+    let removed: HashSet<usize> = done.iter().map(|(idx, _)| *idx).collect();
+    let mut kept: Vec<&str> = content
+        .lines()
+        .enumerate()
+        .filter(|(idx, _)| !removed.contains(idx))
+        .map(|(_, l)| l)
+        .collect();
+    if content.ends_with('\n') {
+        kept.push("");
+    }
+    fs::write(path, kept.join("\n")).map_err(|e| format!("写入失败: {}", e))?;
+    Ok(done.len())
+}
+
+// ---------------- Pomodoro ----------------
+const TODO_SPENT_HEADERS: &[&str] = &["耗时", "用时", "spent"];
+
+struct Pomodoro {
+    task: String,
+    length: Duration,
+    /// 暂停前累计的时长
+    accumulated: Duration,
+    /// 正在计时时为本段开始时刻，暂停时为 None
+    running_since: Option<Instant>,
+}
+
+impl Pomodoro {
+    fn start(task: String, minutes: u64) -> Self {
+        Self {
+            task,
+            length: Duration::from_secs(minutes.max(1) * 60),
+            accumulated: Duration::ZERO,
+            running_since: Some(Instant::now()),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.accumulated + self.running_since.map(|t| t.elapsed()).unwrap_or_default()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed() >= self.length
+    }
+
+    fn toggle_pause(&mut self) {
+        match self.running_since.take() {
+            Some(since) => self.accumulated += since.elapsed(),
+            None => self.running_since = Some(Instant::now()),
+        }
+    }
+
+    fn status_line(&self) -> String {
+        let left = self.length.saturating_sub(self.elapsed()).as_secs();
+        let state = if self.running_since.is_some() {
+            "计时中"
+        } else {
+            "已暂停"
+        };
+        format!(
+            "🍅 {} {:02}:{:02} ({})",
+            self.task,
+            left / 60,
+            left % 60,
+            state
+        )
+    }
+}
+
+/// 解析“耗时”列，支持 `90`、`90m`、`90 min`、`1h30m`、`1.5h`、`1小时30分钟`；
+/// 空白视为 0，无法识别时返回 None
+fn parse_minutes(raw: &str) -> Option<u64> {
+    static DURATION_RE: OnceLock<Regex> = OnceLock::new();
+    let re = DURATION_RE.get_or_init(|| {
+        Regex::new(r"(?i)(\d+(?:\.\d+)?)\s*(hours?|hrs?|h|小时|时|minutes?|mins?|m|分钟|分)?")
+            .expect("valid regex")
+    });
+    let text = raw.trim();
+    if text.is_empty() {
+        return Some(0);
+    }
+    let mut total = 0.0;
+    let mut end = 0;
+    for cap in re.captures_iter(text) {
+        let whole = cap.get(0)?;
+        if !text[end..whole.start()].trim().is_empty() {
+            return None;
+        }
+        end = whole.end();
+        let value: f64 = cap[1].parse().ok()?;
+        let hours = cap.get(2).is_some_and(|unit| {
+            let unit = unit.as_str().to_lowercase();
+            unit.starts_with('h') || unit.contains('时')
+        });
+        total += if hours { value * 60.0 } else { value };
+    }
+    (end > 0 && text[end..].trim().is_empty()).then(|| total.round() as u64)
+}
+
+/// 将耗时累加到“耗时”列；没有该列时追加到 TODO 同名的 `.timelog` 旁路日志
+fn record_todo_time(path: &str, task: &str, spent: Duration) -> Result<String, String> {
+    let minutes = (spent.as_secs() + 30) / 60;
+    if minutes == 0 {
+        return Err("不足1分钟，未记录".into());
+    }
+
+    let content = fs::read_to_string(path).map_err(|_| "文件不存在".to_string())?;
+    let lines = table_lines(&content, 1);
+    let spent_col = lines
+        .first()
+        .and_then(|(_, header)| find_column(header, TODO_SPENT_HEADERS));
+    let status_col = lines
+        .first()
+        .and_then(|(_, header)| find_column(header, TODO_STATUS_HEADERS));
+    let matching: Vec<&(usize, Vec<String>)> = lines
+        .iter()
+        .skip(1)
+        .filter(|(_, row)| !is_separator_row(row) && todo_title(row) == task)
+        .collect();
+    // 重复任务完成后同名的 [x] 行在前，耗时记到未完成的那一行
+    let target = matching
+        .iter()
+        .find(|(_, row)| {
+            !status_col
+                .and_then(|c| row.get(c))
+                .is_some_and(|s| is_done_mark(s))
+        })
+        .or(matching.first())
+        .copied();
+
+    if let (Some(col), Some((line_idx, row))) = (spent_col, target) {
+        let mut row = row.clone();
+        if row.len() <= col {
+            row.resize(col + 1, String::new());
+        }
+        // 已有耗时无法识别时不覆盖，避免丢失手写的记录
+        let recorded = parse_minutes(&row[col])
+            .ok_or_else(|| format!("耗时“{}”无法识别，未记录", row[col].trim()))?;
+        let total = recorded + minutes;
+        row[col] = format!("{}m", total);
+        rewrite_lines(path, &[(*line_idx, format_table_row(&row))], &[])
+            .map_err(|e| format!("写入失败: {}", e))?;
+        return Ok(format!("已记录 {} 分钟，累计 {} 分钟", minutes, total));
+    }
+
+    let log_path = format!("{}.timelog", path);
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map_err(|e| format!("写入失败: {}", e))?;
+    writeln!(
+        log,
+        "{}\t{}m\t{}",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        minutes,
+        task
+    )
+    .map_err(|e| format!("写入失败: {}", e))?;
+    Ok(format!("已记录 {} 分钟至 {}", minutes, log_path))
+}
+
+// ---------------- Cyber resources ----------------
+/// 依次提取单元格中的 Markdown 链接与裸 URL，去重后保持原有顺序
+fn extract_urls(row: &[String]) -> Vec<String> {
+    static URL_RE: OnceLock<Regex> = OnceLock::new();
+    let re = URL_RE.get_or_init(|| {
+        Regex::new(r"\[[^\]]*\]\((https?://[^\s)]+)\)|(https?://[^\s|)<>\]]+)")
+            .expect("valid url regex")
+    });
+    let mut out: Vec<String> = Vec::new();
+    for cell in row {
+        for cap in re.captures_iter(cell) {
+            if let Some(url) = cap.get(1).or_else(|| cap.get(2)) {
+                let url = url.as_str().to_string();
+                if !out.contains(&url) {
+                    out.push(url);
+                }
+            }
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinkAction {
+    Open,
+    Copy,
+}
+
+/// 一行有多个链接时弹出的选择框
+struct LinkPicker {
+    urls: Vec<String>,
+    selected: usize,
+    action: LinkAction,
+}
+
+/// `opener` 可带参数，如 `open -a Firefox`、`firefox --new-tab`，链接作为最后一个参数
+fn open_url(opener: &str, url: &str) -> io::Result<()> {
+    let mut parts = opener.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "未配置 url_opener"))?;
+    let mut child = Command::new(program)
+        .args(parts)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // 后台回收子进程，避免留下僵尸进程
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// 通过 OSC 52 写入系统剪贴板，SSH 会话中同样可用
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()
+}
+
+fn run_link_action(cfg: &Config, action: LinkAction, url: &str) -> String {
+    match action {
+        LinkAction::Open => match open_url(&cfg.url_opener, url) {
+            Ok(_) => format!("已打开 {}", url),
+            Err(e) => format!("打开失败 ({}): {}", cfg.url_opener, e),
+        },
+        LinkAction::Copy => match copy_to_clipboard(url) {
+            Ok(_) => format!("已复制 {}", url),
+            Err(e) => format!("复制失败: {}", e),
+        },
+    }
+}
+
+// ---------------- Link check ----------------
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LinkStatus {
+    Ok,
+    Redirect(String),
+    Http(u16),
+    Timeout,
+    Error(String),
+}
+
+impl LinkStatus {
+    fn badge(&self) -> String {
+        match self {
+            LinkStatus::Ok => "ok".into(),
+            LinkStatus::Redirect(_) => "redirect".into(),
+            LinkStatus::Http(code) => code.to_string(),
+            LinkStatus::Timeout => "timeout".into(),
+            LinkStatus::Error(_) => "error".into(),
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            LinkStatus::Redirect(to) => format!("跳转至 {}", to),
+            LinkStatus::Error(e) => e.clone(),
+            _ => String::new(),
+        }
+    }
+
+    fn is_broken(&self) -> bool {
+        !matches!(self, LinkStatus::Ok | LinkStatus::Redirect(_))
+    }
+}
+
+/// 先发 HEAD，失败或被拒绝时退回 GET；自动跟随跳转，最终地址不同则记为 redirect
+fn check_url(client: &Client, url: &str) -> LinkStatus {
+    let classify = |resp: reqwest::blocking::Response| {
+        let status = resp.status();
+        if !status.is_success() {
+            return LinkStatus::Http(status.as_u16());
+        }
+        let final_url = resp.url().as_str().trim_end_matches('/');
+        if final_url != url.trim_end_matches('/') {
+            LinkStatus::Redirect(resp.url().to_string())
+        } else {
+            LinkStatus::Ok
+        }
+    };
+    let from_err = |e: reqwest::Error| {
+        if e.is_timeout() {
+            LinkStatus::Timeout
+        } else {
+            LinkStatus::Error(e.to_string())
+        }
+    };
+
+    match client.head(url).send() {
+        Ok(resp) if resp.status().is_success() => classify(resp),
+        Ok(_) | Err(_) => match client.get(url).send() {
+            Ok(resp) => classify(resp),
+            Err(e) => from_err(e),
+        },
+    }
+}
+
+/// 以有限并发检查所有链接，结果顺序与输入一致
+fn check_links(client: &Client, urls: &[String], concurrency: usize) -> Vec<LinkStatus> {
+    parallel_map(urls, concurrency, |url| check_url(client, url))
+}
+
+/// 以有限并发对每个元素执行 `f`，结果顺序与输入一致
+fn parallel_map<T, R, F>(items: &[T], concurrency: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let queue = Mutex::new(items.iter().enumerate());
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..items.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let next = queue.lock().map(|mut q| q.next()).unwrap_or(None);
+                let Some((idx, item)) = next else { break };
+                let value = f(item);
+                if let Ok(mut results) = results.lock() {
+                    results[idx] = Some(value);
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap_or_default()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn resource_urls(rows: &[Vec<String>]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for row in rows.iter().skip(1) {
+        for url in extract_urls(row) {
+            if !out.contains(&url) {
+                out.push(url);
+            }
+        }
+    }
+    out
+}
+
+fn link_report_markdown(results: &[(String, LinkStatus)]) -> String {
+    let broken = results.iter().filter(|(_, s)| s.is_broken()).count();
+    let mut out = String::new();
+    let _ = writeln!(out, "# 链接检查报告\n");
+    let _ = writeln!(
+        out,
+        "检查时间：{}，共 {} 个链接，失效 {} 个\n",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        results.len(),
+        broken
+    );
+    out.push_str("| 状态 | 链接 | 说明 |\n|------|------|------|\n");
+    let mut sorted: Vec<&(String, LinkStatus)> = results.iter().collect();
+    sorted.sort_by_key(|(_, s)| !s.is_broken());
+    for (url, status) in sorted {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} |",
+            status.badge(),
+            url,
+            status.detail()
+        );
+    }
+    out
+}
+
+/// 检查资源表中的全部链接并写出 Markdown 报告
+fn run_link_check(
+    cfg: &Config,
+    rows: &[Vec<String>],
+) -> (Vec<(String, LinkStatus)>, io::Result<PathBuf>) {
+    let urls = resource_urls(rows);
+    let client = build_http_client(Duration::from_secs(cfg.link_check_timeout_secs));
+    let statuses = check_links(&client, &urls, cfg.link_check_concurrency);
+    let results: Vec<(String, LinkStatus)> = urls.into_iter().zip(statuses).collect();
+    let report = cfg.link_report_file();
+    let written = fs::write(&report, link_report_markdown(&results)).map(|_| report);
+    (results, written)
+}
+
+/// 在资源表末尾追加一列状态标记，一行多个链接时取最差的结果
+fn cyber_display_rows(
+    rows: &[Vec<String>],
+    statuses: &HashMap<String, LinkStatus>,
+) -> Vec<Vec<String>> {
+    if statuses.is_empty() {
+        return rows.to_vec();
+    }
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut row = row.clone();
+            let badge = if i == 0 {
+                "状态".to_string()
+            } else if is_separator_row(&row) {
+                "---".to_string()
+            } else {
+                let found: Vec<&LinkStatus> = extract_urls(&row)
+                    .iter()
+                    .filter_map(|u| statuses.get(u))
+                    .collect();
+                found
+                    .iter()
+                    .find(|s| s.is_broken())
+                    .or_else(|| found.first())
+                    .map(|s| s.badge())
+                    .unwrap_or_default()
+            };
+            row.push(badge);
+            row
+        })
+        .collect()
+}
+
+const UNCATEGORIZED: &str = "未分类";
+
+fn cyber_category_col(cfg: &Config, rows: &[Vec<String>]) -> Option<usize> {
+    let name = cfg.cyber_category_column.trim().to_lowercase();
+    rows.first().and_then(|h| find_column(h, &[name.as_str()]))
+}
+
+fn cyber_row_category(row: &[String], col: usize) -> &str {
+    match row.get(col).map(|c| c.as_str()) {
+        Some(c) if !c.is_empty() => c,
+        _ => UNCATEGORIZED,
+    }
+}
+
+/// 分类及其资源数，按首次出现顺序排列
+fn cyber_categories(rows: &[Vec<String>], col: usize) -> Vec<(String, usize)> {
+    let mut out: Vec<(String, usize)> = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        if i == 0 || is_separator_row(row) {
+            continue;
+        }
+        let category = cyber_row_category(row, col);
+        match out.iter_mut().find(|(c, _)| c == category) {
+            Some((_, n)) => *n += 1,
+            None => out.push((category.to_string(), 1)),
+        }
+    }
+    out
+}
+
+/// 当前分类下可见的行；`category` 为 None 时显示全部
+fn cyber_visible_rows(
+    rows: &[Vec<String>],
+    col: Option<usize>,
+    category: Option<&str>,
+) -> Vec<usize> {
+    (0..rows.len())
+        .filter(|&i| match (col, category) {
+            (Some(col), Some(category)) => {
+                i == 0
+                    || is_separator_row(&rows[i])
+                    || cyber_row_category(&rows[i], col) == category
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+fn check_links_headless(cfg: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let rows = parse_table(&read_cyber(cfg)?, 1);
+    let (results, written) = run_link_check(cfg, &rows);
+    for (url, status) in &results {
+        println!("{:<8} {} {}", status.badge(), url, status.detail());
+    }
+    let broken = results.iter().filter(|(_, s)| s.is_broken()).count();
+    println!("共 {} 个链接，失效 {} 个", results.len(), broken);
+    println!("报告已写入 {}", written?.display());
+    Ok(())
+}
+
+// ---------------- Bookmark import ----------------
+const CYBER_TITLE_HEADERS: &[&str] = &["名称", "标题", "资源", "title", "name"];
+const CYBER_URL_HEADERS: &[&str] = &["链接", "网址", "地址", "url", "link"];
+const CYBER_CATEGORY_HEADERS: &[&str] = &["分类", "类别", "标签", "category", "tag"];
+
+#[derive(Debug, Clone)]
+struct Bookmark {
+    title: String,
+    url: String,
+    folder: String,
+}
+
+fn decode_html_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// 解析 Firefox / Chrome 导出的 Netscape 书签 HTML，以所在文件夹作为分类
+fn parse_netscape_bookmarks(html: &str) -> Vec<Bookmark> {
+    static BOOKMARK_RE: OnceLock<Regex> = OnceLock::new();
+    let re = BOOKMARK_RE.get_or_init(|| {
+        Regex::new(
+            r#"(?is)<h3[^>]*>(.*?)</h3>|<a\s[^>]*href="([^"]*)"[^>]*>(.*?)</a>|<dl[^>]*>|</dl>"#,
+        )
+        .expect("valid bookmark regex")
+    });
+    let mut folders: Vec<String> = Vec::new();
+    let mut pending: Option<String> = None;
+    let mut out = Vec::new();
+
+    for cap in re.captures_iter(html) {
+        let token = cap.get(0).map(|m| m.as_str()).unwrap_or_default();
+        if let Some(folder) = cap.get(1) {
+            pending = Some(decode_html_entities(folder.as_str().trim()));
+        } else if let Some(url) = cap.get(2) {
+            let url = decode_html_entities(url.as_str().trim());
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                continue;
+            }
+            out.push(Bookmark {
+                title: decode_html_entities(cap.get(3).map(|t| t.as_str()).unwrap_or("").trim()),
+                url,
+                folder: folders.last().cloned().unwrap_or_default(),
+            });
+        } else if token.starts_with("</") {
+            folders.pop();
+        } else {
+            // 最外层 <DL> 没有对应的文件夹标题
+            folders.push(pending.take().unwrap_or_default());
+        }
+    }
+    out
+}
+
+/// 每行一个链接的纯文本列表，链接以外的文字作为标题
+fn parse_url_list(text: &str) -> Vec<Bookmark> {
+    text.lines()
+        .flat_map(|line| {
+            let urls = extract_urls(&[line.to_string()]);
+            let title = urls
+                .iter()
+                .fold(line.to_string(), |acc, u| acc.replace(u.as_str(), ""))
+                .trim_matches(|c: char| c.is_whitespace() || "-:|#".contains(c))
+                .to_string();
+            urls.into_iter().map(move |url| Bookmark {
+                title: title.clone(),
+                url,
+                folder: String::new(),
+            })
+        })
+        .collect()
+}
+
+fn parse_bookmark_file(content: &str) -> Vec<Bookmark> {
+    let lower = content.to_ascii_lowercase();
+    if lower.contains("netscape-bookmark-file") || lower.contains("<dt>") {
+        parse_netscape_bookmarks(content)
+    } else {
+        parse_url_list(content)
+    }
+}
+
+fn sanitize_cell(text: &str) -> String {
+    text.replace('|', "/")
+        .replace(['\n', '\r'], " ")
+        .trim()
+        .to_string()
+}
+
+/// 将书签追加到资源表末尾，已存在的链接跳过；返回 (新增, 跳过)
+fn import_bookmarks(cfg: &Config, source: &Path) -> Result<(usize, usize), String> {
+    let input = fs::read_to_string(source).map_err(|_| "无法读取书签文件".to_string())?;
+    let bookmarks = parse_bookmark_file(&input);
+    if bookmarks.is_empty() {
+        return Err("书签文件中没有链接".into());
+    }
+
+    let path = &cfg.cyber_resource_file_path;
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let lines = table_lines(&content, 1);
+    let rows: Vec<Vec<String>> = lines.iter().map(|(_, r)| r.clone()).collect();
+    let mut known: HashSet<String> = resource_urls(&rows).into_iter().collect();
+
+    let default_header: Vec<String> = vec!["名称".into(), "链接".into(), "分类".into()];
+    let header = rows
+        .first()
+        .cloned()
+        .unwrap_or_else(|| default_header.clone());
+    let title_col = find_column(&header, CYBER_TITLE_HEADERS);
+    let url_col = find_column(&header, CYBER_URL_HEADERS);
+    let category_col = find_column(&header, CYBER_CATEGORY_HEADERS);
+
+    let mut new_rows = Vec::new();
+    let mut skipped = 0usize;
+    for bm in bookmarks {
+        if !known.insert(bm.url.clone()) {
+            skipped += 1;
+            continue;
+        }
+        let title = sanitize_cell(&bm.title);
+        let mut row = vec![String::new(); header.len()];
+        match (title_col, url_col) {
+            (Some(t), Some(u)) => {
+                row[t] = title;
+                row[u] = bm.url;
+            }
+            (None, Some(u)) => row[u] = bm.url,
+            (t, None) => {
+                // 没有链接列时写成 Markdown 链接
+                row[t.unwrap_or(0)] = format!("[{}]({})", title, bm.url);
+            }
+        }
+        if let Some(c) = category_col {
+            row[c] = sanitize_cell(&bm.folder);
+        }
+        new_rows.push(format_table_row(&row));
+    }
+
+    if new_rows.is_empty() {
+        return Ok((0, skipped));
+    }
+    let added = new_rows.len();
+    let result = match lines.last() {
+        Some((last_idx, _)) => rewrite_lines(path, &[], &[(*last_idx, new_rows.join("\n"))]),
+        None => {
+            let mut out = content;
+            if !out.is_empty() && !out.ends_with('\n') {
+                out.push('\n');
+            }
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            let _ = writeln!(
+                out,
+                "{}\n{}\n{}",
+                format_table_row(&default_header),
+                format_table_row(&vec!["---".to_string(); default_header.len()]),
+                new_rows.join("\n")
+            );
+            fs::write(path, out)
+        }
+    };
+    result.map_err(|e| format!("写入失败: {}", e))?;
+    Ok((added, skipped))
+}
+
+fn import_bookmarks_headless(
+    cfg: &Config,
+    source: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let source = source.ok_or("用法: Entry import-bookmarks <书签文件>")?;
+    let (added, skipped) = import_bookmarks(cfg, Path::new(&source))?;
+    println!("已导入 {} 条，跳过重复 {} 条", added, skipped);
+    Ok(())
+}
+
+// ---------------- Page metadata ----------------
+const CYBER_DESC_HEADERS: &[&str] = &["描述", "简介", "说明", "description", "desc"];
+const CYBER_SITE_HEADERS: &[&str] = &["站点", "网站", "site"];
+
+#[derive(Debug, Clone, Default)]
+struct PageMeta {
+    title: String,
+    description: String,
+    site_name: String,
+}
+
+/// 待确认的补全建议：只包含原本为空的单元格
+#[derive(Debug, Clone)]
+struct MetaSuggestion {
+    row: usize,
+    url: String,
+    fills: Vec<(usize, String)>,
+}
+
+fn extract_page_meta(html: &str) -> PageMeta {
+    static TITLE_RE: OnceLock<Regex> = OnceLock::new();
+    static META_RE: OnceLock<Regex> = OnceLock::new();
+    static ATTR_RE: OnceLock<Regex> = OnceLock::new();
+    let title_re = TITLE_RE
+        .get_or_init(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").expect("valid regex"));
+    let meta_re = META_RE.get_or_init(|| Regex::new(r"(?is)<meta\s[^>]*>").expect("valid regex"));
+    let attr_re = ATTR_RE.get_or_init(|| {
+        Regex::new(r#"(?is)([a-z:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid regex")
+    });
+
+    let clean =
+        |s: &str| decode_html_entities(s.split_whitespace().collect::<Vec<_>>().join(" ").as_str());
+    let mut meta = PageMeta::default();
+    let mut og_title = String::new();
+    let mut plain_desc = String::new();
+
+    for tag in meta_re.find_iter(html) {
+        let mut key = String::new();
+        let mut content = String::new();
+        for attr in attr_re.captures_iter(tag.as_str()) {
+            let value = attr
+                .get(2)
+                .or_else(|| attr.get(3))
+                .map(|v| v.as_str())
+                .unwrap_or("");
+            match attr[1].to_ascii_lowercase().as_str() {
+                "property" | "name" => key = value.to_ascii_lowercase(),
+                "content" => content = clean(value),
+                _ => {}
+            }
+        }
+        match key.as_str() {
+            "og:title" => og_title = content,
+            "og:description" => meta.description = content,
+            "description" => plain_desc = content,
+            "og:site_name" => meta.site_name = content,
+            _ => {}
+        }
+    }
+
+    meta.title = title_re
+        .captures(html)
+        .map(|c| clean(&c[1]))
+        .filter(|t| !t.is_empty())
+        .unwrap_or(og_title);
+    if meta.description.is_empty() {
+        meta.description = plain_desc;
+    }
+    meta
+}
+
+/// 标题与 meta 都在页面开头，只读取这么多字节
+const PAGE_META_MAX_BYTES: u64 = 256 * 1024;
+
+/// 只解析 HTML 页面；指向安装包、PDF、视频等的链接不下载正文
+fn fetch_page_meta(client: &Client, url: &str) -> Option<PageMeta> {
+    let resp = client.get(url).send().ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_ascii_lowercase());
+    if let Some(ct) = &content_type {
+        if !ct.contains("text/html") && !ct.contains("application/xhtml") {
+            return None;
+        }
+    }
+    let encoding = content_type
+        .as_deref()
+        .and_then(|ct| ct.split("charset=").nth(1))
+        .and_then(|cs| encoding_rs::Encoding::for_label(cs.trim_matches(['"', ' ']).as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    let mut body = Vec::new();
+    resp.take(PAGE_META_MAX_BYTES).read_to_end(&mut body).ok()?;
+    Some(extract_page_meta(&encoding.decode(&body).0))
+}
+
+/// 找出有链接但标题/描述为空的行，后台抓取网页信息生成补全建议
+fn collect_meta_suggestions(cfg: &Config, rows: &[Vec<String>]) -> Vec<MetaSuggestion> {
+    let Some(header) = rows.first() else {
+        return Vec::new();
+    };
+    let Some(url_col) = find_column(header, CYBER_URL_HEADERS) else {
+        return Vec::new();
+    };
+    let title_col = find_column(header, CYBER_TITLE_HEADERS);
+    let desc_col = find_column(header, CYBER_DESC_HEADERS);
+    let site_col = find_column(header, CYBER_SITE_HEADERS);
+    let is_empty = |row: &Vec<String>, col: Option<usize>| {
+        col.is_some_and(|c| row.get(c).map(|v| v.is_empty()).unwrap_or(true))
+    };
+
+    let targets: Vec<(usize, String)> = rows
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, row)| !is_separator_row(row))
+        .filter(|(_, row)| {
+            is_empty(row, title_col) || is_empty(row, desc_col) || is_empty(row, site_col)
+        })
+        .filter_map(|(i, row)| {
+            let url = extract_urls(&[row.get(url_col)?.clone()])
+                .into_iter()
+                .next()?;
+            Some((i, url))
+        })
+        .collect();
+
+    let client = build_http_client(Duration::from_secs(cfg.link_check_timeout_secs));
+    let metas = parallel_map(&targets, cfg.link_check_concurrency, |(_, url)| {
+        fetch_page_meta(&client, url)
+    });
+
+    targets
+        .into_iter()
+        .zip(metas)
+        .filter_map(|((row, url), meta)| {
+            let meta = meta?;
+            let fills: Vec<(usize, String)> = [
+                (title_col, meta.title),
+                (desc_col, meta.description),
+                (site_col, meta.site_name),
+            ]
+            .into_iter()
+            .filter(|(col, value)| !value.is_empty() && is_empty(&rows[row], *col))
+            .filter_map(|(col, value)| Some((col?, sanitize_cell(&value))))
+            .collect();
+            (!fills.is_empty()).then_some(MetaSuggestion { row, url, fills })
+        })
+        .collect()
+}
+
+/// 将确认后的建议写回资源表对应行
+fn apply_meta_suggestion(path: &str, suggestion: &MetaSuggestion) -> Result<(), String> {
+    let content = fs::read_to_string(path).map_err(|_| "文件不存在".to_string())?;
+    let lines = table_lines(&content, 1);
+    let (line_idx, row) = lines
+        .get(suggestion.row)
+        .filter(|(_, row)| extract_urls(row).contains(&suggestion.url))
+        .ok_or("资源表已变化，请重新获取")?;
+    let mut row = row.clone();
+    for (col, value) in &suggestion.fills {
+        if row.len() <= *col {
+            row.resize(col + 1, String::new());
+        }
+        if row[*col].is_empty() {
+            row[*col] = value.clone();
+        }
+    }
+    rewrite_lines(path, &[(*line_idx, format_table_row(&row))], &[])
+        .map_err(|e| format!("写入失败: {}", e))
+}
+
+// ---------------- Bill analysis ----------------
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BillEntry {
+    /// 账单来源，如“微信”“支付宝”
+    source: String,
+    time: Option<NaiveDateTime>,
+    partner: String,
+    product: String,
+    amount: f64,
+    /// 由分类规则填写，未命中时沿用平台分类，仍为空则为“未分类”
+    #[serde(skip)]
+    category: String,
+    /// 平台自带的分类：微信“交易类型”、支付宝“交易分类”
+    platform_category: String,
+    payment_method: String,
+    status: String,
+    flow: BillFlow,
+    /// 支出：已被退款冲抵的金额；退款：已匹配到原交易的金额
+    #[serde(skip)]
+    refunded: f64,
+    /// 平台订单号：微信“交易单号”、支付宝“交易订单号”
+    order_id: String,
+    /// 退款：匹配到的原支出的去重键，未匹配时为空
+    #[serde(skip)]
+    refund_of: String,
+}
+
+/// FNV-1a 64 位哈希。结果写入账本，需要跨版本稳定，因此不用标准库的 DefaultHasher
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// 资金流向。转账、还款、余额宝转入等“不计收支”的记录记为 Transfer，不计入收支。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum BillFlow {
+    Expense,
+    Income,
+    Transfer,
+    Refund,
+}
+
+impl BillFlow {
+    fn label(self) -> &'static str {
+        match self {
+            BillFlow::Expense => "支出",
+            BillFlow::Income => "收入",
+            BillFlow::Transfer => "不计收支",
+            BillFlow::Refund => "退款",
+        }
+    }
+
+    /// 根据“收/支”列与交易类型、状态、商品判断流向；收/支为空时返回 None
+    fn classify(direction: &str, hints: &[&str]) -> Option<BillFlow> {
+        let direction = direction.trim();
+        if direction.is_empty() {
+            return None;
+        }
+        if direction.contains("不计") || direction == "/" {
+            // 支付宝的退款多记为“不计收支”
+            return Some(if hints.iter().any(|h| h.contains("退款")) {
+                BillFlow::Refund
+            } else {
+                BillFlow::Transfer
+            });
+        }
+        if direction.contains('支') {
+            Some(BillFlow::Expense)
+        } else if hints.iter().any(|h| h.contains("退款")) {
+            Some(BillFlow::Refund)
+        } else if direction.contains('收') {
+            Some(BillFlow::Income)
+        } else {
+            Some(BillFlow::Transfer)
+        }
+    }
+}
+
+impl BillEntry {
+    /// 去重键：优先使用订单号，没有时用时间+金额+交易对方的哈希
+    fn dedupe_key(&self) -> String {
+        if !self.order_id.is_empty() {
+            return self.order_id.clone();
+        }
+        let time = self.time.map(|t| t.to_string()).unwrap_or_default();
+        let raw = format!("{}|{:.2}|{}", time, self.amount, self.partner.trim());
+        format!("#{:016x}", fnv1a64(raw.as_bytes()))
+    }
+
+    /// 计入统计的金额：支出扣除已冲抵的退款，转账与退款本身不计
+    fn net_amount(&self) -> f64 {
+        match self.flow {
+            BillFlow::Expense => self.amount - self.refunded,
+            BillFlow::Income => self.amount,
+            BillFlow::Transfer | BillFlow::Refund => 0.0,
+        }
+    }
+}
+
+// ---- 分类规则 ----
+#[derive(Debug, Deserialize)]
+struct BillRulesFile {
+    #[serde(default)]
+    rule: Vec<BillRuleConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BillRuleConfig {
+    category: String,
+    pattern: String,
+    /// partner / product，留空表示两者任一匹配即可
+    #[serde(default)]
+    field: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RuleField {
+    Partner,
+    Product,
+    Any,
+}
+
+#[derive(Debug, Clone)]
+struct BillRule {
+    category: String,
+    pattern: Regex,
+    field: RuleField,
+}
+
+impl BillRule {
+    fn matches(&self, entry: &BillEntry) -> bool {
+        match self.field {
+            RuleField::Partner => self.pattern.is_match(&entry.partner),
+            RuleField::Product => self.pattern.is_match(&entry.product),
+            RuleField::Any => {
+                self.pattern.is_match(&entry.partner) || self.pattern.is_match(&entry.product)
+            }
+        }
+    }
+}
+
+const BILL_RULES_TEMPLATE: &str = r#"# 账单分类规则：自上而下匹配，第一条命中的规则决定分类
+# field 可选 partner（交易对方）/ product（商品），省略时两者任一匹配即可
+
+[[rule]]
+category = "餐饮"
+pattern = "美团|饿了么|肯德基|麦当劳|瑞幸|星巴克"
+
+[[rule]]
+category = "交通"
+pattern = "滴滴|地铁|公交|12306|高德打车"
+
+[[rule]]
+category = "房租"
+pattern = "房租|租金"
+field = "product"
+"#;
+
+/// 读取分类规则；文件不存在时视为没有规则
+fn load_bill_rules(path: &Path) -> Result<Vec<BillRule>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("读取分类规则失败: {}", e))?;
+    let file: BillRulesFile =
+        toml::from_str(&content).map_err(|e| format!("分类规则格式错误: {}", e))?;
+    file.rule
+        .into_iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let pattern = Regex::new(&r.pattern)
+                .map_err(|e| format!("第 {} 条规则的正则无效: {}", i + 1, e))?;
+            let field = match r.field.trim().to_lowercase().as_str() {
+                "" | "any" => RuleField::Any,
+                "partner" | "交易对方" => RuleField::Partner,
+                "product" | "商品" => RuleField::Product,
+                other => return Err(format!("第 {} 条规则的字段未知: {}", i + 1, other)),
+            };
+            Ok(BillRule {
+                category: r.category.trim().to_string(),
+                pattern,
+                field,
+            })
+        })
+        .collect()
+}
+
+fn categorize_entry(entry: &BillEntry, rules: &[BillRule]) -> String {
+    rules
+        .iter()
+        .find(|r| r.matches(entry))
+        .map(|r| r.category.clone())
+        .or_else(|| {
+            let platform = entry.platform_category.trim();
+            (!platform.is_empty() && platform != "/").then(|| platform.to_string())
+        })
+        .unwrap_or_else(|| UNCATEGORIZED.to_string())
+}
+
+#[derive(Debug, Clone)]
+struct CategorySummary {
+    category: String,
+    count: usize,
+    amount: f64,
+}
+
+// ---- 跨平台配对 ----
+/// 疑似同一笔消费在两份账单中各出现一次，确认后删除 drop
+#[derive(Debug, Clone)]
+struct BillPair {
+    keep: BillEntry,
+    drop: BillEntry,
+    similarity: f64,
+}
+
+/// 支付渠道在银行流水中的常见写法，对应账单来源
+const PAYMENT_CHANNELS: [(&str, &str); 4] = [
+    ("财付通", "微信"),
+    ("微信", "微信"),
+    ("支付宝", "支付宝"),
+    ("alipay", "支付宝"),
+];
+
+fn normalize_partner(raw: &str) -> String {
+    let mut s = raw.trim().to_lowercase();
+    for (channel, _) in PAYMENT_CHANNELS {
+        s = s.replace(channel, "");
+    }
+    s.chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+fn bigrams(s: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = s.chars().collect();
+    chars.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+/// 交易对方名称相似度（0~1）：包含关系记 1，否则按二元组 Dice 系数；
+/// 一方只写了支付渠道（如“财付通”）且与另一方来源一致时记 0.6
+fn partner_similarity(a: &BillEntry, b: &BillEntry) -> f64 {
+    let (na, nb) = (normalize_partner(&a.partner), normalize_partner(&b.partner));
+    if na.is_empty() || nb.is_empty() {
+        let via_channel = |e: &BillEntry, other: &BillEntry| {
+            let lower = e.partner.to_lowercase();
+            PAYMENT_CHANNELS
+                .iter()
+                .any(|(channel, source)| lower.contains(channel) && other.source == *source)
+        };
+        return if via_channel(a, b) || via_channel(b, a) {
+            0.6
+        } else {
+            0.0
+        };
+    }
+    if na.contains(&nb) || nb.contains(&na) {
+        return 1.0;
+    }
+    let (ga, gb) = (bigrams(&na), bigrams(&nb));
+    if ga.is_empty() || gb.is_empty() {
+        return 0.0;
+    }
+    let common = ga.iter().filter(|g| gb.contains(g)).count();
+    2.0 * common as f64 / (ga.len() + gb.len()) as f64
+}
+
+/// 微信、支付宝账单信息更全，配对时优先保留
+fn source_rank(source: &str) -> u8 {
+    match source {
+        "微信" | "支付宝" => 0,
+        _ => 1,
+    }
+}
+
+const PAIR_MIN_SIMILARITY: f64 = 0.5;
+
+/// 按月或按周汇总的一行：标签（如 2024-05 / 2024-W19）与收支合计。
+#[derive(Debug, Clone)]
+struct PeriodSummary {
+    label: String,
+    income: f64,
+    expense: f64,
+}
+
+impl PeriodSummary {
+    fn net(&self) -> f64 {
+        self.income - self.expense
+    }
+}
+
+fn month_label(time: &NaiveDateTime) -> String {
+    time.format("%Y-%m").to_string()
+}
+
+fn week_label(time: &NaiveDateTime) -> String {
+    let week = time.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// 环比变化百分比；上期为零时无意义，返回 None。
+fn change_percent(prev: f64, current: f64) -> Option<f64> {
+    if prev.abs() < f64::EPSILON {
+        None
+    } else {
+        Some((current - prev) / prev * 100.0)
+    }
+}
+
+fn format_change(change: Option<f64>) -> String {
+    match change {
+        Some(pct) => format!("{:+.1}%", pct),
+        None => "-".to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct BillAggregate {
+    entries: Vec<BillEntry>,
+}
+
+impl BillAggregate {
+    fn from_entries(entries: Vec<BillEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// 合并另一份账单，跳过已存在的交易（同一份账单内部不去重），返回丢弃的笔数
+    fn merge_unique(&mut self, other: BillAggregate) -> usize {
+        let seen: HashSet<String> = self.entries.iter().map(|e| e.dedupe_key()).collect();
+        let incoming = other.entries.len();
+        let before = self.entries.len();
+        self.entries.extend(
+            other
+                .entries
+                .into_iter()
+                .filter(|e| !seen.contains(&e.dedupe_key())),
+        );
+        incoming - (self.entries.len() - before)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn by_flow(&self, flow: BillFlow) -> impl Iterator<Item = &BillEntry> {
+        self.entries.iter().filter(move |e| e.flow == flow)
+    }
+
+    fn total_income(&self) -> f64 {
+        self.by_flow(BillFlow::Income).map(|e| e.net_amount()).sum()
+    }
+
+    fn total_expense(&self) -> f64 {
+        self.by_flow(BillFlow::Expense)
+            .map(|e| e.net_amount())
+            .sum()
+    }
+
+    fn net(&self) -> f64 {
+        self.total_income() - self.total_expense()
+    }
+
+    /// 找出不同来源间金额相同、时间相近、交易对方相似的支出，每笔最多参与一对
+    fn find_cross_pairs(
+        &self,
+        window: ChronoDuration,
+        rejected: &HashSet<(String, String)>,
+    ) -> Vec<BillPair> {
+        let mut by_cents: HashMap<i64, Vec<&BillEntry>> = HashMap::new();
+        for e in self.by_flow(BillFlow::Expense) {
+            by_cents
+                .entry((e.amount * 100.0).round() as i64)
+                .or_default()
+                .push(e);
+        }
+
+        let mut candidates: Vec<(f64, ChronoDuration, &BillEntry, &BillEntry)> = Vec::new();
+        for group in by_cents.values() {
+            for (i, a) in group.iter().enumerate() {
+                for b in &group[i + 1..] {
+                    if a.source == b.source {
+                        continue;
+                    }
+                    let (Some(ta), Some(tb)) = (a.time, b.time) else {
+                        continue;
+                    };
+                    let gap = (ta - tb).abs();
+                    if gap > window {
+                        continue;
+                    }
+                    let (keep, drop) = if source_rank(&b.source) < source_rank(&a.source) {
+                        (*b, *a)
+                    } else {
+                        (*a, *b)
+                    };
+                    if rejected.contains(&(keep.dedupe_key(), drop.dedupe_key())) {
+                        continue;
+                    }
+                    let similarity = partner_similarity(a, b);
+                    if similarity >= PAIR_MIN_SIMILARITY {
+                        candidates.push((similarity, gap, keep, drop));
+                    }
+                }
+            }
+        }
+        candidates.sort_by(|a, b| {
+            b.0.partial_cmp(&a.0)
+                .unwrap_or(Ordering::Equal)
+                .then(a.1.cmp(&b.1))
+        });
+
+        let mut used: HashSet<String> = HashSet::new();
+        let mut pairs = Vec::new();
+        for (similarity, _, keep, drop) in candidates {
+            let (kk, dk) = (keep.dedupe_key(), drop.dedupe_key());
+            if used.contains(&kk) || used.contains(&dk) {
+                continue;
+            }
+            used.insert(kk);
+            used.insert(dk);
+            pairs.push(BillPair {
+                keep: keep.clone(),
+                drop: drop.clone(),
+                similarity,
+            });
+        }
+        pairs.sort_by_key(|p| p.keep.time);
+        pairs
+    }
+
+    /// 删除第一笔去重键相同的交易
+    fn remove_entry(&mut self, key: &str) -> bool {
+        match self.entries.iter().position(|e| e.dedupe_key() == key) {
+            Some(i) => {
+                self.entries.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 把退款匹配回原支出：同一交易对方、不晚于退款时间、剩余金额足够，
+    /// 优先商品名一致的，其次时间最近的。返回未能匹配的退款笔数。
+    fn match_refunds(&mut self) -> usize {
+        for e in &mut self.entries {
+            e.refunded = 0.0;
+            e.refund_of.clear();
+        }
+        let mut refunds: Vec<usize> = (0..self.entries.len())
+            .filter(|&i| self.entries[i].flow == BillFlow::Refund)
+            .collect();
+        refunds.sort_by_key(|&i| self.entries[i].time);
+
+        let mut unmatched = 0;
+        for r in refunds {
+            let refund = self.entries[r].clone();
+            let product = refund.product.trim_start_matches("退款-").trim();
+            let best = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| {
+                    e.flow == BillFlow::Expense
+                        && e.partner.trim() == refund.partner.trim()
+                        && e.amount - e.refunded + 0.005 >= refund.amount
+                        && match (e.time, refund.time) {
+                            (Some(paid), Some(back)) => paid <= back,
+                            _ => true,
+                        }
+                })
+                .max_by_key(|(_, e)| (!product.is_empty() && e.product.contains(product), e.time))
+                .map(|(i, _)| i);
+            match best {
+                Some(i) => {
+                    self.entries[i].refunded += refund.amount;
+                    self.entries[r].refunded = refund.amount;
+                    self.entries[r].refund_of = self.entries[i].dedupe_key();
+                }
+                None => unmatched += 1,
+            }
+        }
+        self.sync_refund_categories();
+        unmatched
+    }
+
+    /// 按给定标签函数分组汇总，结果按标签升序排列；没有时间的记录不参与。
+    fn periods(&self, label: fn(&NaiveDateTime) -> String) -> Vec<PeriodSummary> {
+        let mut map: HashMap<String, (f64, f64)> = HashMap::new();
+        for e in &self.entries {
+            let Some(t) = &e.time else { continue };
+            match e.flow {
+                BillFlow::Income => map.entry(label(t)).or_default().0 += e.net_amount(),
+                BillFlow::Expense => map.entry(label(t)).or_default().1 += e.net_amount(),
+                BillFlow::Transfer | BillFlow::Refund => {}
+            }
+        }
+        let mut list: Vec<PeriodSummary> = map
+            .into_iter()
+            .map(|(label, (income, expense))| PeriodSummary {
+                label,
+                income,
+                expense,
+            })
+            .collect();
+        list.sort_by(|a, b| a.label.cmp(&b.label));
+        list
+    }
+
+    /// 去掉状态命中过滤列表的记录，返回去掉的笔数
+    fn exclude_statuses(&mut self, excluded: &[String]) -> usize {
+        let keep = |e: &BillEntry| {
+            !excluded
+                .iter()
+                .any(|s| !s.trim().is_empty() && e.status.contains(s.trim()))
+        };
+        let before = self.entries.len();
+        self.entries.retain(keep);
+        before - self.entries.len()
+    }
+
+    fn apply_rules(&mut self, rules: &[BillRule]) {
+        for e in &mut self.entries {
+            e.category = categorize_entry(e, rules);
+        }
+        self.sync_refund_categories();
+    }
+
+    /// 已匹配的退款沿用原支出的分类：平台给退款的分类（如“退款”）与原交易不同，
+    /// 记账时要冲回原支出的账户
+    fn sync_refund_categories(&mut self) {
+        let categories: HashMap<String, String> = self
+            .entries
+            .iter()
+            .filter(|e| e.flow == BillFlow::Expense)
+            .map(|e| (e.dedupe_key(), e.category.clone()))
+            .collect();
+        for e in &mut self.entries {
+            if let Some(category) = categories.get(&e.refund_of) {
+                e.category = category.clone();
+            }
+        }
+    }
+
+    /// 支出按分类汇总，金额从高到低
+    fn expense_by_category(&self) -> Vec<CategorySummary> {
+        let mut map: HashMap<&str, (usize, f64)> = HashMap::new();
+        for e in self.by_flow(BillFlow::Expense) {
+            let slot = map.entry(e.category.as_str()).or_default();
+            slot.0 += 1;
+            slot.1 += e.net_amount();
+        }
+        let mut list: Vec<CategorySummary> = map
+            .into_iter()
+            .map(|(category, (count, amount))| CategorySummary {
+                category: category.to_string(),
+                count,
+                amount,
+            })
+            .collect();
+        list.sort_by(|a, b| {
+            b.amount
+                .partial_cmp(&a.amount)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.category.cmp(&b.category))
+        });
+        list
+    }
+
+    /// 支出最多的交易对方：(名称, 金额, 笔数)
+    fn top_partners(&self, n: usize) -> Vec<(String, f64, usize)> {
+        let mut map: HashMap<&str, (f64, usize)> = HashMap::new();
+        for e in self.by_flow(BillFlow::Expense) {
+            let slot = map.entry(e.partner.trim()).or_default();
+            slot.0 += e.net_amount();
+            slot.1 += 1;
+        }
+        let mut list: Vec<(String, f64, usize)> = map
+            .into_iter()
+            .map(|(name, (amount, count))| (name.to_string(), amount, count))
+            .collect();
+        list.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        list.truncate(n);
+        list
+    }
+
+    /// 只保留日期范围内的交易；设置了范围时没有时间的交易也被排除
+    fn in_range(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> BillAggregate {
+        if from.is_none() && to.is_none() {
+            return self.clone();
+        }
+        let entries = self
+            .entries
+            .iter()
+            .filter(|e| {
+                e.time.map(|t| t.date()).is_some_and(|day| {
+                    from.is_none_or(|from| day >= from) && to.is_none_or(|to| day <= to)
+                })
+            })
+            .cloned()
+            .collect();
+        BillAggregate::from_entries(entries)
+    }
+
+    fn monthly(&self) -> Vec<PeriodSummary> {
+        self.periods(month_label)
+    }
+
+    fn weekly(&self) -> Vec<PeriodSummary> {
+        self.periods(week_label)
+    }
+
+    fn to_markdown(&self) -> String {
+        let sorted = |flow: BillFlow| {
+            let mut list: Vec<BillEntry> = self.by_flow(flow).cloned().collect();
+            list.sort_by(|a, b| b.amount.partial_cmp(&a.amount).unwrap_or(Ordering::Equal));
+            list
+        };
+        let expenses_sorted = sorted(BillFlow::Expense);
+        let incomes_sorted = sorted(BillFlow::Income);
+        let refunds = sorted(BillFlow::Refund);
+        let transfers = sorted(BillFlow::Transfer);
+
+        let mut out = String::new();
+        let _ = writeln!(&mut out, "# 账单分析\n");
+
+        let _ = writeln!(out, "## 支出");
+        if expenses_sorted.is_empty() {
+            out.push_str("无支出记录。\n");
+        } else {
+            out.push_str(&entries_markdown(&expenses_sorted));
+            let _ = writeln!(out, "\n总支出：{:.2} 元\n", self.total_expense());
+        }
+
+        let _ = writeln!(out, "## 收入");
+        if incomes_sorted.is_empty() {
+            out.push_str("无收入记录。\n");
+        } else {
+            out.push_str(&entries_markdown(&incomes_sorted));
+            let _ = writeln!(out, "\n总收入：{:.2} 元\n", self.total_income());
+        }
+
+        if !refunds.is_empty() {
+            let _ = writeln!(out, "## 退款\n");
+            out.push_str(&entries_markdown(&refunds));
+            let unmatched = refunds.iter().filter(|e| e.refunded <= 0.0).count();
+            let _ = writeln!(
+                out,
+                "\n已冲抵原支出：{:.2} 元；未找到原交易（不计入统计）：{} 笔\n",
+                refunds.iter().map(|e| e.refunded).sum::<f64>(),
+                unmatched
+            );
+        }
+
+        if !transfers.is_empty() {
+            let _ = writeln!(out, "## 不计收支\n");
+            out.push_str(&entries_markdown(&transfers));
+            let _ = writeln!(
+                out,
+                "\n合计：{:.2} 元（转账、还款、理财转入等，不计入收支）\n",
+                transfers.iter().map(|e| e.amount).sum::<f64>()
+            );
+        }
+
+        let net = self.net();
+        let label = if net >= 0.0 { "净收入" } else { "净支出" };
+        let _ = writeln!(out, "{}：{:.2} 元\n", label, net.abs());
+
+        let categories = self.expense_by_category();
+        if !categories.is_empty() {
+            let _ = writeln!(out, "## 支出分类\n");
+            out.push_str("| 分类 | 笔数 | 金额(元) | 占比 |\n|------|------|----------|------|\n");
+            for row in category_rows(&categories, self.total_expense()) {
+                let _ = writeln!(out, "| {} |", row.join(" | "));
+            }
+            out.push('\n');
+        }
+
+        let monthly = self.monthly();
+        if !monthly.is_empty() {
+            let _ = writeln!(out, "## 按月汇总\n");
+            out.push_str(&period_markdown("月份", &monthly));
+        }
+        let weekly = self.weekly();
+        if !weekly.is_empty() {
+            let _ = writeln!(out, "\n## 按周汇总\n");
+            out.push_str(&period_markdown("周", &weekly));
+        }
+
+        out
+    }
+}
+
+fn entries_markdown(entries: &[BillEntry]) -> String {
+    let mut out = String::from(
+        "| 时间 | 交易对方 | 商品 | 分类 | 支付方式 | 状态 | 金额(元) |\n|------|----------|------|------|----------|------|----------|\n",
+    );
+    for e in entries {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} | {} |",
+            format_bill_time(e.time.as_ref()),
+            e.partner,
+            e.product,
+            e.category,
+            e.payment_method,
+            e.status,
+            format_entry_amount(e)
+        );
+    }
+    out
+}
+
+fn format_entry_amount(e: &BillEntry) -> String {
+    match e.flow {
+        BillFlow::Expense if e.refunded > 0.0 => {
+            format!("{:.2}（已退 {:.2}）", e.amount, e.refunded)
+        }
+        BillFlow::Refund if e.refunded <= 0.0 => format!("{:.2}（未匹配）", e.amount),
+        _ => format!("{:.2}", e.amount),
+    }
+}
+
+fn format_bill_time(time: Option<&NaiveDateTime>) -> String {
+    time.map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// 汇总表的数据行（不含表头），最后一列为支出环比。
+fn period_rows(periods: &[PeriodSummary]) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut prev: Option<f64> = None;
+    for p in periods {
+        let change = prev.and_then(|prev| change_percent(prev, p.expense));
+        rows.push(vec![
+            p.label.clone(),
+            format!("{:.2}", p.income),
+            format!("{:.2}", p.expense),
+            format!("{:.2}", p.net()),
+            format_change(change),
+        ]);
+        prev = Some(p.expense);
+    }
+    rows
+}
+
+/// 分类汇总表的数据行（不含表头），占比相对总支出。
+fn category_rows(categories: &[CategorySummary], total: f64) -> Vec<Vec<String>> {
+    categories
+        .iter()
+        .map(|c| {
+            let share = if total.abs() < f64::EPSILON {
+                0.0
+            } else {
+                c.amount / total * 100.0
+            };
+            vec![
+                c.category.clone(),
+                c.count.to_string(),
+                format!("{:.2}", c.amount),
+                format!("{:.1}%", share),
+            ]
+        })
+        .collect()
+}
+
+fn period_markdown(label: &str, periods: &[PeriodSummary]) -> String {
+    let mut out = format!(
+        "| {} | 收入(元) | 支出(元) | 结余(元) | 支出环比 |\n|------|----------|----------|----------|----------|\n",
+        label
+    );
+    for row in period_rows(periods) {
+        let _ = writeln!(out, "| {} |", row.join(" | "));
+    }
+    out
+}
+
+// ---- 账本 ----
+/// 账本中的一行记录，按追加顺序回放即可恢复分析结果
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LedgerRecord {
+    /// 一份已导入的账单，按内容哈希识别
+    File {
+        hash: String,
+        name: String,
+        imported_at: String,
+        entries: usize,
+        excluded: usize,
+        duplicates: usize,
+    },
+    Entry(BillEntry),
+    /// 确认合并的跨平台重复，记录被删除一侧的去重键
+    Merge {
+        drop: String,
+    },
+    Reject {
+        keep: String,
+        drop: String,
+    },
+}
+
+/// 追加写入的 JSON Lines 账本，位于账单目录下
+struct BillLedger {
+    path: PathBuf,
+}
+
+impl BillLedger {
+    fn load(&self) -> Result<Vec<LedgerRecord>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path).map_err(|e| format!("读取账本失败: {}", e))?;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| format!("账本第 {} 行损坏: {}", i + 1, e))
+            })
+            .collect()
+    }
+
+    fn append(&self, records: &[LedgerRecord]) -> Result<(), String> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut buf = String::new();
+        for record in records {
+            let line = serde_json::to_string(record).map_err(|e| format!("写入账本失败: {}", e))?;
+            buf.push_str(&line);
+            buf.push('\n');
+        }
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(buf.as_bytes()))
+            .map_err(|e| format!("写入账本失败: {}", e))
+    }
+}
+
+fn file_hash(path: &Path) -> Option<String> {
+    fs::read(path)
+        .ok()
+        .map(|bytes| format!("{:016x}", fnv1a64(&bytes)))
+}
+
+struct BillState {
+    bill_dir: PathBuf,
+    ledger: BillLedger,
+    /// 账本是否已读入；首次进入账单页时加载
+    loaded: bool,
+    /// 已导入账单的内容哈希
+    imported: HashSet<String>,
+    /// 账单目录中各文件的内容哈希，刷新时计算
+    file_hashes: HashMap<PathBuf, String>,
+    rules_path: PathBuf,
+    exclude_statuses: Vec<String>,
+    /// 因交易状态被过滤掉的笔数
+    excluded: usize,
+    unmatched_refunds: usize,
+    /// 与已导入账单重复而被丢弃的笔数
+    duplicates: usize,
+    pair_window: ChronoDuration,
+    /// 待确认的跨平台重复
+    pairs: VecDeque<BillPair>,
+    rejected_pairs: HashSet<(String, String)>,
+    merged_pairs: usize,
+    files: Vec<PathBuf>,
+    /// 最近一次按 a 分析时每份账单的结果
+    results: Vec<FileResult>,
+    aggregate: BillAggregate,
+    tab: BillTab,
+    browser: TxBrowser,
+    budgets: HashMap<String, f64>,
+    accounts: AccountConfig,
+    formats: Vec<BillFormatConfig>,
+}
+
+/// 单份账单的分析结果
+#[derive(Debug, Clone)]
+struct FileResult {
+    name: String,
+    ok: bool,
+    detail: String,
+}
+
+impl BillState {
+    fn new(cfg: &Config) -> Self {
+        let dir = PathBuf::from(&cfg.bill_dir_path);
+        Self {
+            ledger: BillLedger {
+                path: dir.join("ledger.jsonl"),
+            },
+            bill_dir: dir,
+            loaded: false,
+            imported: HashSet::new(),
+            file_hashes: HashMap::new(),
+            rules_path: cfg.bill_rules_file(),
+            exclude_statuses: cfg.bill_exclude_statuses.clone(),
+            excluded: 0,
+            unmatched_refunds: 0,
+            duplicates: 0,
+            pair_window: ChronoDuration::hours(cfg.bill_pair_window_hours.max(0)),
+            pairs: VecDeque::new(),
+            rejected_pairs: HashSet::new(),
+            merged_pairs: 0,
+            files: Vec::new(),
+            results: Vec::new(),
+            tab: BillTab::Summary,
+            browser: TxBrowser::default(),
+            budgets: cfg.budgets.clone(),
+            accounts: cfg.accounts.clone(),
+            formats: cfg.bill_formats.clone(),
+            aggregate: BillAggregate::default(),
+        }
+    }
+
+    fn refresh_files(&mut self) -> io::Result<()> {
+        if !self.bill_dir.exists() {
+            fs::create_dir_all(&self.bill_dir)?;
+        }
+
+        let mut list: Vec<PathBuf> = fs::read_dir(&self.bill_dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| {
+                        let ext_lc = ext.to_ascii_lowercase();
+                        ext_lc == "xlsx" || ext_lc == "xls" || ext_lc == "csv"
+                    })
+                    .unwrap_or(false)
+            })
+            .collect();
+        list.sort();
+        self.file_hashes = list
+            .iter()
+            .filter_map(|p| file_hash(p).map(|h| (p.clone(), h)))
+            .collect();
+        self.files = list;
+        Ok(())
+    }
+
+    fn budgets(&self, day: NaiveDate) -> Vec<BudgetStatus> {
+        budget_statuses(&self.budgets, &self.aggregate, day)
+    }
+
+    fn is_imported(&self, path: &Path) -> bool {
+        self.file_hashes
+            .get(path)
+            .is_some_and(|h| self.imported.contains(h))
+    }
+
+    fn pending_count(&self) -> usize {
+        self.files.iter().filter(|p| !self.is_imported(p)).count()
+    }
+
+    /// 回放账本，恢复之前会话的分析结果；只在首次调用时读取
+    fn load_ledger(&mut self) -> Result<(), String> {
+        if self.loaded {
+            return Ok(());
+        }
+        let records = self.ledger.load()?;
+        self.loaded = true;
+        for record in records {
+            match record {
+                LedgerRecord::File {
+                    hash,
+                    excluded,
+                    duplicates,
+                    ..
+                } => {
+                    self.imported.insert(hash);
+                    self.excluded += excluded;
+                    self.duplicates += duplicates;
+                }
+                LedgerRecord::Entry(entry) => self.aggregate.entries.push(entry),
+                LedgerRecord::Merge { drop } => {
+                    if self.aggregate.remove_entry(&drop) {
+                        self.merged_pairs += 1;
+                    }
+                }
+                LedgerRecord::Reject { keep, drop } => {
+                    self.rejected_pairs.insert((keep, drop));
+                }
+            }
+        }
+        self.unmatched_refunds = self.aggregate.match_refunds();
+        let rules = load_bill_rules(&self.rules_path);
+        self.aggregate
+            .apply_rules(rules.as_deref().unwrap_or_default());
+        rules.map(|_| ())
+    }
+
+    /// 逐份分析未导入的账单，单份失败不影响其余账单；每份的结果记录在 results 中
+    fn analyze_pending(&mut self) -> Result<usize, String> {
+        self.load_ledger()?;
+        let rules = load_bill_rules(&self.rules_path)?;
+        self.results.clear();
+        let mut success = 0usize;
+        for path in &self.files {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let Some(hash) = self.file_hashes.get(path).cloned() else {
+                self.results.push(FileResult {
+                    name,
+                    ok: false,
+                    detail: "无法读取文件".into(),
+                });
+                continue;
+            };
+            if self.imported.contains(&hash) {
+                continue;
+            }
+            match analyze_bill_file(path, &self.formats) {
+                Ok((format, mut report)) => {
+                    let excluded = report.exclude_statuses(&self.exclude_statuses);
+                    let start = self.aggregate.entries.len();
+                    let duplicates = self.aggregate.merge_unique(report);
+                    let added = &self.aggregate.entries[start..];
+
+                    let mut records = vec![LedgerRecord::File {
+                        hash: hash.clone(),
+                        name: name.clone(),
+                        imported_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                        entries: added.len(),
+                        excluded,
+                        duplicates,
+                    }];
+                    records.extend(added.iter().cloned().map(LedgerRecord::Entry));
+                    let added = added.len();
+                    if let Err(e) = self.ledger.append(&records) {
+                        self.aggregate.entries.truncate(start);
+                        self.results.push(FileResult {
+                            name,
+                            ok: false,
+                            detail: e,
+                        });
+                        continue;
+                    }
+
+                    self.imported.insert(hash);
+                    self.excluded += excluded;
+                    self.duplicates += duplicates;
+                    success += 1;
+                    self.results.push(FileResult {
+                        name,
+                        ok: true,
+                        detail: format!(
+                            "{}：导入 {} 笔，重复 {} 笔，排除 {} 笔",
+                            format, added, duplicates, excluded
+                        ),
+                    });
+                }
+                Err(e) => self.results.push(FileResult {
+                    name,
+                    ok: false,
+                    detail: e,
+                }),
+            }
+        }
+        let failed = self.results.iter().filter(|r| !r.ok).count();
+        if success == 0 {
+            return Err(if failed > 0 {
+                format!("{} 份账单分析失败，详见列表", failed)
+            } else {
+                "没有需要分析的账单".into()
+            });
+        }
+        self.aggregate.apply_rules(&rules);
+        self.unmatched_refunds = self.aggregate.match_refunds();
+        Ok(success)
+    }
+
+    /// 查找跨平台重复，放入待确认队列，返回找到的对数
+    fn find_pairs(&mut self) -> usize {
+        self.pairs = self
+            .aggregate
+            .find_cross_pairs(self.pair_window, &self.rejected_pairs)
+            .into();
+        self.pairs.len()
+    }
+
+    fn confirm_pair(&mut self) -> Result<(), String> {
+        if let Some(pair) = self.pairs.pop_front() {
+            let drop = pair.drop.dedupe_key();
+            self.ledger
+                .append(&[LedgerRecord::Merge { drop: drop.clone() }])?;
+            if self.aggregate.remove_entry(&drop) {
+                self.merged_pairs += 1;
+                self.unmatched_refunds = self.aggregate.match_refunds();
+            }
+        }
+        Ok(())
+    }
+
+    fn reject_pair(&mut self) -> Result<(), String> {
+        if let Some(pair) = self.pairs.pop_front() {
+            let (keep, drop) = (pair.keep.dedupe_key(), pair.drop.dedupe_key());
+            self.ledger.append(&[LedgerRecord::Reject {
+                keep: keep.clone(),
+                drop: drop.clone(),
+            }])?;
+            self.rejected_pairs.insert((keep, drop));
+        }
+        Ok(())
+    }
+
+    /// 重新读取规则并为已分析的记录分类，返回未分类的支出笔数
+    fn reapply_rules(&mut self) -> Result<usize, String> {
+        let rules = load_bill_rules(&self.rules_path)?;
+        self.aggregate.apply_rules(&rules);
+        Ok(self
+            .aggregate
+            .by_flow(BillFlow::Expense)
+            .filter(|e| e.category == UNCATEGORIZED)
+            .count())
+    }
+
+    /// 打开规则文件编辑，不存在时先写入示例规则
+    fn edit_rules(&self) -> Result<(), String> {
+        if !self.rules_path.exists() {
+            if let Some(parent) = self.rules_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+            }
+            fs::write(&self.rules_path, BILL_RULES_TEMPLATE)
+                .map_err(|e| format!("写入规则模板失败: {}", e))?;
+        }
+        open_in_neovim(&self.rules_path.to_string_lossy())
+            .map_err(|e| format!("打开编辑器失败: {}", e))
+    }
+
+    /// 按所选格式导出；报表覆盖写出，记账分录只追加账本中还没有的交易
+    fn export_reports(
+        &self,
+        target_dir: &Path,
+        options: &ExportOptions,
+    ) -> io::Result<ExportSummary> {
+        let mut summary = ExportSummary::default();
+        let aggregate = self.aggregate.in_range(options.from, options.to);
+        if aggregate.is_empty() {
+            return Ok(summary);
+        }
+        fs::create_dir_all(target_dir)?;
+        for format in &options.formats {
+            let path = target_dir.join(format.file_name());
+            match format {
+                ExportFormat::Markdown => fs::write(path, aggregate.to_markdown())?,
+                ExportFormat::Csv => fs::write(path, transactions_csv(&aggregate)?)?,
+                ExportFormat::Json => fs::write(path, transactions_json(&aggregate)?)?,
+                ExportFormat::Html => fs::write(path, html_report(&aggregate, options))?,
+                ExportFormat::Beancount | ExportFormat::Hledger => {
+                    let mut journals = vec![path.clone()];
+                    journals.extend(self.accounts.journals.iter().map(PathBuf::from));
+                    let known = journal_txids(&journals);
+                    let (text, added, skipped) =
+                        journal_entries(&aggregate, &self.accounts, *format, &known);
+                    if added > 0 {
+                        let mut file = fs::OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(&path)?;
+                        file.write_all(text.as_bytes())?;
+                    }
+                    summary.journal_added += added;
+                    summary.journal_skipped += skipped;
+                }
+            }
+            summary.files += 1;
+        }
+        Ok(summary)
+    }
+}
+
+// ---- 导出 ----
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Markdown,
+    Csv,
+    Json,
+    Html,
+    Beancount,
+    Hledger,
+}
+
+impl ExportFormat {
+    const ALL: [ExportFormat; 6] = [
+        ExportFormat::Markdown,
+        ExportFormat::Csv,
+        ExportFormat::Json,
+        ExportFormat::Html,
+        ExportFormat::Beancount,
+        ExportFormat::Hledger,
+    ];
+
+    fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            "html" | "htm" => Some(ExportFormat::Html),
+            "beancount" | "bean" => Some(ExportFormat::Beancount),
+            "hledger" | "journal" | "ledger" => Some(ExportFormat::Hledger),
+            _ => None,
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "bill_summary.md",
+            ExportFormat::Csv => "bill_transactions.csv",
+            ExportFormat::Json => "bill_transactions.json",
+            ExportFormat::Html => "bill_report.html",
+            ExportFormat::Beancount => "bill.beancount",
+            ExportFormat::Hledger => "bill.journal",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ExportSummary {
+    files: usize,
+    /// 追加到账本的分录数
+    journal_added: usize,
+    /// 账本中已有 txid 而跳过的交易数
+    journal_skipped: usize,
+}
+
+#[derive(Debug, Clone)]
+struct ExportOptions {
+    formats: Vec<ExportFormat>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+impl ExportOptions {
+    /// 格式用逗号或空格分隔，留空为全部格式
+    fn parse(formats: &str, range: &str) -> Result<Self, String> {
+        let mut list = Vec::new();
+        for raw in formats
+            .split([',', '，', ' '])
+            .filter(|s| !s.trim().is_empty())
+        {
+            let format =
+                ExportFormat::parse(raw).ok_or_else(|| format!("未知导出格式: {}", raw))?;
+            if !list.contains(&format) {
+                list.push(format);
+            }
+        }
+        if list.is_empty() {
+            list = ExportFormat::ALL.to_vec();
+        }
+        let (from, to) = parse_date_range(range)?;
+        Ok(Self {
+            formats: list,
+            from,
+            to,
+        })
+    }
+
+    fn range_label(&self) -> String {
+        let fmt = |d: Option<NaiveDate>| {
+            d.map(|d| d.format(DATE_FMT).to_string())
+                .unwrap_or_else(|| "不限".to_string())
+        };
+        format!("{} ~ {}", fmt(self.from), fmt(self.to))
+    }
+}
+
+/// 统一字段的交易记录，供 CSV / JSON 导出
+#[derive(Debug, Serialize)]
+struct ExportRow<'a> {
+    source: &'a str,
+    time: String,
+    flow: &'static str,
+    partner: &'a str,
+    product: &'a str,
+    category: &'a str,
+    platform_category: &'a str,
+    payment_method: &'a str,
+    status: &'a str,
+    order_id: &'a str,
+    amount: f64,
+    refunded: f64,
+    /// 计入统计的金额：支出为负，收入为正，转账与退款为 0
+    net: f64,
+}
+
+const EXPORT_CSV_HEADERS: [&str; 13] = [
+    "来源",
+    "时间",
+    "收支",
+    "交易对方",
+    "商品",
+    "分类",
+    "平台分类",
+    "支付方式",
+    "交易状态",
+    "订单号",
+    "金额",
+    "已退款",
+    "计入金额",
+];
+
+fn export_rows(aggregate: &BillAggregate) -> Vec<ExportRow<'_>> {
+    let mut entries: Vec<&BillEntry> = aggregate.entries.iter().collect();
+    entries.sort_by_key(|e| e.time);
+    entries
+        .into_iter()
+        .map(|e| ExportRow {
+            source: &e.source,
+            time: e
+                .time
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            flow: e.flow.label(),
+            partner: &e.partner,
+            product: &e.product,
+            category: &e.category,
+            platform_category: &e.platform_category,
+            payment_method: &e.payment_method,
+            status: &e.status,
+            order_id: &e.order_id,
+            amount: e.amount,
+            refunded: e.refunded,
+            net: match e.flow {
+                BillFlow::Expense => -e.net_amount(),
+                _ => e.net_amount(),
+            },
+        })
+        .collect()
+}
+
+fn transactions_csv(aggregate: &BillAggregate) -> io::Result<Vec<u8>> {
+    // 带 BOM，Excel 打开中文不乱码
+    let mut writer = csv::Writer::from_writer("\u{feff}".as_bytes().to_vec());
+    writer.write_record(EXPORT_CSV_HEADERS)?;
+    for r in export_rows(aggregate) {
+        writer.write_record([
+            r.source.to_string(),
+            r.time,
+            r.flow.to_string(),
+            r.partner.to_string(),
+            r.product.to_string(),
+            r.category.to_string(),
+            r.platform_category.to_string(),
+            r.payment_method.to_string(),
+            r.status.to_string(),
+            r.order_id.to_string(),
+            format!("{:.2}", r.amount),
+            format!("{:.2}", r.refunded),
+            format!("{:.2}", r.net),
+        ])?;
+    }
+    writer.into_inner().map_err(|e| e.into_error())
+}
+
+fn transactions_json(aggregate: &BillAggregate) -> io::Result<String> {
+    serde_json::to_string_pretty(&export_rows(aggregate)).map_err(io::Error::from)
+}
+
+fn escape_html(raw: &str) -> String {
+    raw.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut out = String::from("<table class=\"sortable\"><thead><tr>");
+    for h in headers {
+        let _ = write!(out, "<th>{}</th>", escape_html(h));
+    }
+    out.push_str("</tr></thead><tbody>\n");
+    for row in rows {
+        out.push_str("<tr>");
+        for cell in row {
+            let _ = write!(out, "<td>{}</td>", escape_html(cell));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</tbody></table>\n");
+    out
+}
+
+/// 分类支出横向条形图
+fn svg_category_bars(categories: &[CategorySummary]) -> String {
+    const ROW: usize = 26;
+    const LABEL: f64 = 130.0;
+    const WIDTH: f64 = 640.0;
+    let max = categories
+        .iter()
+        .map(|c| c.amount)
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let height = categories.len() * ROW + 10;
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = WIDTH,
+        h = height
+    );
+    for (i, c) in categories.iter().enumerate() {
+        let y = i * ROW + 5;
+        let len = (WIDTH - LABEL - 90.0) * c.amount.max(0.0) / max;
+        let _ = write!(
+            out,
+            "<text x=\"{lx}\" y=\"{ty}\" text-anchor=\"end\">{name}</text>\
+             <rect x=\"{x}\" y=\"{y}\" width=\"{len:.1}\" height=\"18\" fill=\"#e4572e\"/>\
+             <text x=\"{vx:.1}\" y=\"{ty}\">{value:.2}</text>",
+            lx = LABEL - 8.0,
+            ty = y + 14,
+            name = escape_html(&c.category),
+            x = LABEL,
+            vx = LABEL + len + 6.0,
+            value = c.amount,
+        );
+    }
+    out.push_str("</svg>");
+    out
+}
+
+/// 按月收入 / 支出折线图
+fn svg_monthly_lines(monthly: &[PeriodSummary]) -> String {
+    const WIDTH: f64 = 640.0;
+    const HEIGHT: f64 = 260.0;
+    const PAD: f64 = 50.0;
+    let max = monthly
+        .iter()
+        .map(|p| p.income.max(p.expense))
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let step = if monthly.len() > 1 {
+        (WIDTH - 2.0 * PAD) / (monthly.len() - 1) as f64
+    } else {
+        0.0
+    };
+    let point = |i: usize, v: f64| {
+        (
+            PAD + step * i as f64,
+            HEIGHT - PAD - (HEIGHT - 2.0 * PAD) * v / max,
+        )
+    };
+    let polyline = |values: Vec<f64>, color: &str| {
+        let points: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let (x, y) = point(i, *v);
+                format!("{:.1},{:.1}", x, y)
+            })
+            .collect();
+        format!(
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"/>",
+            color,
+            points.join(" ")
+        )
+    };
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = WIDTH,
+        h = HEIGHT
+    );
+    let _ = write!(
+        out,
+        "<line x1=\"{p}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\" stroke=\"#999\"/>\
+         <line x1=\"{p}\" y1=\"{p}\" x2=\"{p}\" y2=\"{b}\" stroke=\"#999\"/>\
+         <text x=\"{tx}\" y=\"{ty}\" text-anchor=\"end\">{max:.0}</text>",
+        p = PAD,
+        b = HEIGHT - PAD,
+        r = WIDTH - PAD,
+        tx = PAD - 6.0,
+        ty = PAD + 4.0,
+        max = max,
+    );
+    for (i, p) in monthly.iter().enumerate() {
+        let (x, _) = point(i, 0.0);
+        let _ = write!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            x,
+            HEIGHT - PAD + 18.0,
+            escape_html(&p.label)
+        );
+    }
+    out.push_str(&polyline(
+        monthly.iter().map(|p| p.income).collect(),
+        "#2a9d8f",
+    ));
+    out.push_str(&polyline(
+        monthly.iter().map(|p| p.expense).collect(),
+        "#e4572e",
+    ));
+    let _ = write!(
+        out,
+        "<text x=\"{x}\" y=\"20\" fill=\"#2a9d8f\">— 收入</text>\
+         <text x=\"{x2}\" y=\"20\" fill=\"#e4572e\">— 支出</text>",
+        x = WIDTH - 170.0,
+        x2 = WIDTH - 90.0,
+    );
+    out.push_str("</svg>");
+    out
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;max-width:960px;margin:2em auto;color:#222}\
+table{border-collapse:collapse;width:100%;margin:1em 0}\
+th,td{border:1px solid #ddd;padding:4px 8px;text-align:left}\
+th{background:#f3f3f3;cursor:pointer;user-select:none}\
+th:hover{background:#e6e6e6}\
+svg text{font-size:12px}";
+
+/// 点击表头排序；整格是数字（可带正负号与 %）时按数值比较，日期等其余文字按字符串比较
+const HTML_SORT_SCRIPT: &str =
+    "function num(s){s=s.trim();return /^[+-]?\\d+(\\.\\d+)?%?$/.test(s)?parseFloat(s):NaN;}\
+document.querySelectorAll('table.sortable th').forEach(function(th,col){\
+th.addEventListener('click',function(){\
+var body=th.closest('table').tBodies[0];var rows=Array.from(body.rows);\
+var asc=th.dataset.asc!=='1';th.dataset.asc=asc?'1':'0';\
+rows.sort(function(a,b){var x=a.cells[col].textContent,y=b.cells[col].textContent;\
+var nx=num(x),ny=num(y);\
+var r=(!isNaN(nx)&&!isNaN(ny))?nx-ny:x.localeCompare(y,'zh');return asc?r:-r;});\
+rows.forEach(function(r){body.appendChild(r);});});});";
+
+/// 单文件 HTML 报表：样式、SVG 图表与排序脚本都内嵌在页面中
+fn html_report(aggregate: &BillAggregate, options: &ExportOptions) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html lang=\"zh-CN\"><head><meta charset=\"utf-8\"><title>账单分析</title>",
+    );
+    let _ = writeln!(out, "<style>{}</style></head><body>", HTML_STYLE);
+    let _ = writeln!(
+        out,
+        "<h1>账单分析</h1><p>日期范围：{}</p>",
+        options.range_label()
+    );
+
+    let net = aggregate.net();
+    let _ = writeln!(
+        out,
+        "<p>总收入 {:.2} 元 · 总支出 {:.2} 元 · {} {:.2} 元</p>",
+        aggregate.total_income(),
+        aggregate.total_expense(),
+        if net >= 0.0 { "净收入" } else { "净支出" },
+        net.abs()
+    );
+
+    let categories = aggregate.expense_by_category();
+    if !categories.is_empty() {
+        out.push_str("<h2>支出分类</h2>\n");
+        out.push_str(&svg_category_bars(&categories));
+        out.push_str(&html_table(
+            &["分类", "笔数", "金额(元)", "占比"],
+            &category_rows(&categories, aggregate.total_expense()),
+        ));
+    }
+
+    let monthly = aggregate.monthly();
+    if !monthly.is_empty() {
+        out.push_str("<h2>按月收支</h2>\n");
+        out.push_str(&svg_monthly_lines(&monthly));
+        out.push_str(&html_table(
+            &["月份", "收入(元)", "支出(元)", "结余(元)", "支出环比"],
+            &period_rows(&monthly),
+        ));
+    }
+
+    out.push_str("<h2>交易明细</h2>\n");
+    let rows: Vec<Vec<String>> = export_rows(aggregate)
+        .into_iter()
+        .map(|r| {
+            vec![
+                r.time,
+                r.source.to_string(),
+                r.flow.to_string(),
+                r.partner.to_string(),
+                r.product.to_string(),
+                r.category.to_string(),
+                r.status.to_string(),
+                format!("{:.2}", r.amount),
+                format!("{:.2}", r.net),
+            ]
+        })
+        .collect();
+    out.push_str(&html_table(
+        &[
+            "时间",
+            "来源",
+            "收支",
+            "交易对方",
+            "商品",
+            "分类",
+            "状态",
+            "金额",
+            "计入金额",
+        ],
+        &rows,
+    ));
+    let _ = write!(
+        out,
+        "<script>{}</script>\n</body></html>\n",
+        HTML_SORT_SCRIPT
+    );
+    out
+}
+
+// ---- 记账分录 ----
+/// 从已有账本中收集 `txid` 元数据（Beancount 的 `txid: "…"` 与 hledger 的 `; txid:…` 标签）
+fn journal_txids(paths: &[PathBuf]) -> HashSet<String> {
+    static TXID_RE: OnceLock<Regex> = OnceLock::new();
+    let re = TXID_RE.get_or_init(|| Regex::new(r#"txid:\s*"?([^",\s]+)"?"#).expect("valid regex"));
+    paths
+        .iter()
+        .filter_map(|p| fs::read_to_string(p).ok())
+        .flat_map(|text| {
+            re.captures_iter(&text)
+                .map(|c| c[1].to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn beancount_string(raw: &str) -> String {
+    format!("\"{}\"", raw.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// 生成尚未记账的分录文本，返回（文本，新增笔数，跳过笔数）。
+/// 支出按原金额记账，退款另记一笔冲回分类账户；不计收支的转账与没有时间的交易不导出。
+fn journal_entries(
+    aggregate: &BillAggregate,
+    accounts: &AccountConfig,
+    format: ExportFormat,
+    known: &HashSet<String>,
+) -> (String, usize, usize) {
+    let mut entries: Vec<&BillEntry> = aggregate
+        .entries
+        .iter()
+        .filter(|e| e.flow != BillFlow::Transfer && e.time.is_some() && e.amount > 0.0)
+        .collect();
+    entries.sort_by_key(|e| e.time);
+
+    let mut out = String::new();
+    let (mut added, mut skipped) = (0, 0);
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    for e in entries {
+        // 没有订单号时同一天同额同对方的交易键相同，按出现次序加后缀区分；
+        // 顺序固定，重复导出得到的 txid 不变
+        let key = e.dedupe_key();
+        let n = occurrences.entry(key.clone()).or_insert(0);
+        *n += 1;
+        let txid = if *n == 1 {
+            key
+        } else {
+            format!("{}-{}", key, n)
+        };
+        if known.contains(&txid) {
+            skipped += 1;
+            continue;
+        }
+        let Some(time) = e.time else { continue };
+        let funding = accounts.funding_account(e);
+        let target = accounts.category_account(e);
+        // 第一条分录记金额，第二条留空由记账软件自动配平
+        let (first, amount, second) = match e.flow {
+            BillFlow::Income | BillFlow::Refund => (funding, e.amount, target),
+            _ => (target, e.amount, funding),
+        };
+        let date = time.date().format(DATE_FMT);
+        match format {
+            ExportFormat::Beancount => {
+                let _ = writeln!(
+                    out,
+                    "\n{} * {} {}",
+                    date,
+                    beancount_string(&e.partner),
+                    beancount_string(&e.product)
+                );
+                let _ = writeln!(out, "  txid: {}", beancount_string(&txid));
+                let _ = writeln!(out, "  source: {}", beancount_string(&e.source));
+                let _ = writeln!(out, "  {}  {:.2} {}", first, amount, accounts.currency);
+                let _ = writeln!(out, "  {}", second);
+            }
+            _ => {
+                let description = format!("{} | {}", e.partner, e.product).replace(';', "；");
+                let _ = writeln!(
+                    out,
+                    "\n{} * {}  ; txid:{}, source:{}",
+                    date,
+                    description.trim(),
+                    txid,
+                    e.source
+                );
+                let _ = writeln!(out, "    {}  {:.2} {}", first, amount, accounts.currency);
+                let _ = writeln!(out, "    {}", second);
+            }
+        }
+        added += 1;
+    }
+    (out, added, skipped)
+}
+
+fn prompt_export_options() -> Result<ExportOptions, String> {
+    let formats = prompt_line(
+        "导出格式 md / csv / json / html / beancount / hledger，逗号分隔（回车导出全部）:",
+    )
+    .map_err(|e| format!("输入导出格式失败: {}", e))?;
+    let range = prompt_line("日期范围，如 2024-05-01~2024-05-31，可只写一端（回车不限）:")
+        .map_err(|e| format!("输入日期范围失败: {}", e))?;
+    ExportOptions::parse(&formats, &range)
+}
+
+// ---- 预算 ----
+/// 某分类本月的预算执行情况
+#[derive(Debug, Clone)]
+struct BudgetStatus {
+    category: String,
+    limit: f64,
+    spent: f64,
+}
+
+impl BudgetStatus {
+    fn ratio(&self) -> f64 {
+        if self.limit <= 0.0 {
+            0.0
+        } else {
+            self.spent / self.limit
+        }
+    }
+
+    /// 超过 80% 黄色，超过 100% 红色
+    fn color(&self) -> Color {
+        let ratio = self.ratio();
+        if ratio > 1.0 {
+            Color::Red
+        } else if ratio > 0.8 {
+            Color::Yellow
+        } else {
+            Color::Green
+        }
+    }
+}
+
+/// 按配置的预算统计指定月份各分类的支出，使用比例高的排在前面
+fn budget_statuses(
+    budgets: &HashMap<String, f64>,
+    aggregate: &BillAggregate,
+    month: NaiveDate,
+) -> Vec<BudgetStatus> {
+    let mut spent: HashMap<&str, f64> = HashMap::new();
+    for e in aggregate.by_flow(BillFlow::Expense) {
+        let Some(t) = e.time else { continue };
+        if t.year() == month.year() && t.month() == month.month() {
+            *spent.entry(e.category.as_str()).or_default() += e.net_amount();
+        }
+    }
+    let mut list: Vec<BudgetStatus> = budgets
+        .iter()
+        .map(|(category, limit)| BudgetStatus {
+            category: category.clone(),
+            limit: *limit,
+            spent: spent.get(category.as_str()).copied().unwrap_or(0.0),
+        })
+        .collect();
+    list.sort_by(|a, b| {
+        b.ratio()
+            .partial_cmp(&a.ratio())
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.category.cmp(&b.category))
+    });
+    list
+}
+
+/// 主菜单上的一行预算概况，没有配置预算时返回 None
+fn budget_summary_line(statuses: &[BudgetStatus]) -> Option<Line<'static>> {
+    if statuses.is_empty() {
+        return None;
+    }
+    let mut spans = vec![Span::raw(format!("{}月预算 ", today().month()))];
+    for st in statuses {
+        spans.push(Span::styled(
+            format!("{} {:.0}% ", st.category, st.ratio() * 100.0),
+            Style::default().fg(st.color()),
+        ));
+    }
+    let over = statuses.iter().filter(|s| s.ratio() > 1.0).count();
+    if over > 0 {
+        spans.push(Span::styled(
+            format!("| {} 项超支", over),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+    Some(Line::from(spans))
+}
+
+/// 解析“起始~结束”日期范围，任一端可省略；只写一个日期表示当天，整体留空表示不限
+fn parse_date_range(input: &str) -> Result<(Option<NaiveDate>, Option<NaiveDate>), String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok((None, None));
+    }
+    let (a, b) = input.split_once('~').unwrap_or((input, input));
+    let parse = |raw: &str| -> Result<Option<NaiveDate>, String> {
+        if raw.trim().is_empty() {
+            Ok(None)
+        } else {
+            parse_date(raw)
+                .map(Some)
+                .ok_or_else(|| format!("无法识别日期: {}", raw.trim()))
+        }
+    };
+    Ok((parse(a)?, parse(b)?))
+}
+
+// ---- 交易浏览 ----
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BillTab {
+    Summary,
+    Transactions,
+    Charts,
+    Budgets,
+}
+
+impl BillTab {
+    const ALL: [BillTab; 4] = [
+        BillTab::Summary,
+        BillTab::Transactions,
+        BillTab::Charts,
+        BillTab::Budgets,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            BillTab::Summary => "概览",
+            BillTab::Transactions => "交易",
+            BillTab::Charts => "图表",
+            BillTab::Budgets => "预算",
+        }
+    }
+
+    fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum TxSort {
+    #[default]
+    Time,
+    Amount,
+    Partner,
+    Category,
+}
+
+impl TxSort {
+    fn label(self) -> &'static str {
+        match self {
+            TxSort::Time => "时间",
+            TxSort::Amount => "金额",
+            TxSort::Partner => "交易对方",
+            TxSort::Category => "分类",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            TxSort::Time => TxSort::Amount,
+            TxSort::Amount => TxSort::Partner,
+            TxSort::Partner => TxSort::Category,
+            TxSort::Category => TxSort::Time,
+        }
+    }
+}
+
+const TX_FLOWS: [BillFlow; 4] = [
+    BillFlow::Expense,
+    BillFlow::Income,
+    BillFlow::Refund,
+    BillFlow::Transfer,
+];
+
+/// 交易表的排序、搜索与筛选状态；selected 为筛选结果中的下标
+#[derive(Debug, Default)]
+struct TxBrowser {
+    sort: TxSort,
+    /// 默认按时间倒序，最近的在最前
+    ascending: bool,
+    query: String,
+    flow: Option<BillFlow>,
+    category: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    selected: usize,
+    detail: bool,
+}
+
+impl TxBrowser {
+    fn matches(&self, e: &BillEntry) -> bool {
+        if self.flow.is_some_and(|f| f != e.flow) {
+            return false;
+        }
+        if self.category.as_ref().is_some_and(|c| *c != e.category) {
+            return false;
+        }
+        if self.from.is_some() || self.to.is_some() {
+            let Some(day) = e.time.map(|t| t.date()) else {
+                return false;
+            };
+            if self.from.is_some_and(|from| day < from) || self.to.is_some_and(|to| day > to) {
+                return false;
+            }
+        }
+        if !self.query.is_empty() {
+            let q = self.query.to_lowercase();
+            let fields = [
+                &e.partner,
+                &e.product,
+                &e.category,
+                &e.payment_method,
+                &e.status,
+                &e.order_id,
+            ];
+            if !fields.iter().any(|f| f.to_lowercase().contains(&q)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn visible<'a>(&self, aggregate: &'a BillAggregate) -> Vec<&'a BillEntry> {
+        let mut list: Vec<&BillEntry> = aggregate
+            .entries
+            .iter()
+            .filter(|e| self.matches(e))
+            .collect();
+        list.sort_by(|a, b| {
+            let ord = match self.sort {
+                TxSort::Time => a.time.cmp(&b.time),
+                TxSort::Amount => a.amount.partial_cmp(&b.amount).unwrap_or(Ordering::Equal),
+                TxSort::Partner => a.partner.cmp(&b.partner),
+                TxSort::Category => a.category.cmp(&b.category),
+            };
+            if self.ascending {
+                ord
+            } else {
+                ord.reverse()
+            }
+        });
+        list
+    }
+
+    fn rows(entries: &[&BillEntry]) -> Vec<Vec<String>> {
+        let mut rows = vec![["时间", "交易对方", "商品", "分类", "收支", "金额"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect::<Vec<_>>()];
+        rows.extend(entries.iter().map(|e| {
+            vec![
+                format_bill_time(e.time.as_ref()),
+                e.partner.clone(),
+                e.product.clone(),
+                e.category.clone(),
+                e.flow.label().to_string(),
+                format_entry_amount(e),
+            ]
+        }));
+        rows
+    }
+
+    /// 当前排序与筛选条件的简述，显示在表格标题上
+    fn describe(&self) -> String {
+        let mut parts = vec![format!(
+            "按{}{}",
+            self.sort.label(),
+            if self.ascending { "升序" } else { "降序" }
+        )];
+        if let Some(flow) = self.flow {
+            parts.push(flow.label().to_string());
+        }
+        if let Some(category) = &self.category {
+            parts.push(format!("分类: {}", category));
+        }
+        if self.from.is_some() || self.to.is_some() {
+            let fmt = |d: Option<NaiveDate>| d.map(|d| d.format(DATE_FMT).to_string());
+            parts.push(format!(
+                "{} ~ {}",
+                fmt(self.from).unwrap_or_default(),
+                fmt(self.to).unwrap_or_default()
+            ));
+        }
+        if !self.query.is_empty() {
+            parts.push(format!("搜索: {}", self.query));
+        }
+        parts.join(" | ")
+    }
+
+    /// 分析、合并或重新分类后可见交易可能变少，选中行不能越界
+    fn clamp_selection(&mut self, len: usize) {
+        self.selected = self.selected.min(len.saturating_sub(1));
+        if len == 0 {
+            self.detail = false;
+        }
+    }
+
+    fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+        self.selected = 0;
+    }
+
+    fn toggle_order(&mut self) {
+        self.ascending = !self.ascending;
+        self.selected = 0;
+    }
+
+    fn cycle_flow(&mut self) {
+        self.flow = match self.flow {
+            None => Some(TX_FLOWS[0]),
+            Some(flow) => {
+                let i = TX_FLOWS.iter().position(|f| *f == flow).unwrap_or(0);
+                TX_FLOWS.get(i + 1).copied()
+            }
+        };
+        self.selected = 0;
+    }
+
+    /// 在出现过的分类之间轮换，最后回到“全部”
+    fn cycle_category(&mut self, aggregate: &BillAggregate) {
+        let mut categories: Vec<&str> = aggregate
+            .entries
+            .iter()
+            .map(|e| e.category.as_str())
+            .collect();
+        categories.sort();
+        categories.dedup();
+        self.category = match &self.category {
+            None => categories.first().map(|c| c.to_string()),
+            Some(current) => categories
+                .iter()
+                .position(|c| c == current)
+                .and_then(|i| categories.get(i + 1))
+                .map(|c| c.to_string()),
+        };
+        self.selected = 0;
+    }
+
+    fn set_range(&mut self, input: &str) -> Result<(), String> {
+        let (from, to) = parse_date_range(input)?;
+        self.from = from;
+        self.to = to;
+        self.selected = 0;
+        Ok(())
+    }
+
+    fn clear_filters(&mut self) {
+        self.query.clear();
+        self.flow = None;
+        self.category = None;
+        self.from = None;
+        self.to = None;
+        self.selected = 0;
+    }
+}
+
+fn cell_to_string(cell: &DataType) -> String {
+    match cell {
+        DataType::String(s) => s.clone(),
+        DataType::Float(f) => format!("{:.2}", f),
+        DataType::Int(i) => i.to_string(),
+        DataType::Bool(b) => b.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// 解析账单中的交易时间，兼容各平台与银行常见的几种写法。
+fn parse_datetime(raw: &str) -> Option<NaiveDateTime> {
+    let raw = raw.trim();
+    const FORMATS: [&str; 6] = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y%m%d %H:%M:%S",
+    ];
+    FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(raw, fmt).ok())
+        .or_else(|| {
+            parse_date(raw)
+                .or_else(|| NaiveDate::parse_from_str(raw, "%Y%m%d").ok())
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+fn cell_to_datetime(cell: &DataType) -> Option<NaiveDateTime> {
+    match cell {
+        DataType::String(s) | DataType::DateTimeIso(s) => parse_datetime(s),
+        DataType::DateTime(serial) | DataType::Float(serial) => excel_serial_datetime(*serial),
+        _ => None,
+    }
+}
+
+/// Excel 序列日期：以 1899-12-30 为第 0 天，小数部分为当天时间
+fn excel_serial_datetime(serial: f64) -> Option<NaiveDateTime> {
+    let base = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+    let secs = (serial * 86_400.0).round() as i64;
+    Some(base + ChronoDuration::seconds(secs))
+}
+
+/// 账单时间列：文字日期，或未设置日期格式的 Excel 序列值（约 1954–2173 年）
+fn parse_bill_time(raw: &str) -> Option<NaiveDateTime> {
+    parse_datetime(raw).or_else(|| {
+        raw.trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|serial| (20_000.0..100_000.0).contains(serial))
+            .and_then(excel_serial_datetime)
+    })
+}
+
+fn parse_amount(raw: &str) -> Option<f64> {
+    let mut buf = String::new();
+    for ch in raw.chars() {
+        if ch.is_ascii_digit() || ch == '.' || ch == '-' {
+            buf.push(ch);
+        }
+    }
+    if buf.is_empty() {
+        None
+    } else {
+        buf.parse().ok()
+    }
+}
+
+// ---- 账单格式 ----
+/// 账单表格：CSV 与 Excel 统一读成字符串网格，供各解析器嗅探表头与逐行解析
+struct BillTable {
+    rows: Vec<Vec<String>>,
+}
+
+impl BillTable {
+    /// 表头通常在前几十行之内，之前是平台或银行写的说明文字
+    const HEADER_SCAN_ROWS: usize = 60;
+
+    fn load(path: &Path) -> Result<Self, String> {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|s| s.to_ascii_lowercase())
+            .ok_or_else(|| "无法识别文件类型".to_string())?;
+        let rows = match ext.as_str() {
+            "xlsx" | "xls" => {
+                let mut workbook =
+                    open_workbook_auto(path).map_err(|_| "无法打开文件".to_string())?;
+                let range = workbook
+                    .worksheet_range_at(0)
+                    .ok_or_else(|| "账单缺少工作表".to_string())
+                    .and_then(|r| r.map_err(|_| "读取工作表失败".to_string()))?;
+                range
+                    .rows()
+                    .map(|row| row.iter().map(cell_to_text).collect())
+                    .collect()
+            }
+            "csv" => {
+                let bytes = fs::read(path).map_err(|_| "无法读取文件".to_string())?;
+                let content = decode_bill_text(bytes);
+                let mut reader = ReaderBuilder::new()
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_reader(content.as_bytes());
+                let mut rows = Vec::new();
+                for record in reader.records() {
+                    let record = record.map_err(|_| "解析CSV失败".to_string())?;
+                    rows.push(record.iter().map(|c| c.trim().to_string()).collect());
+                }
+                rows
+            }
+            _ => return Err("不支持的账单格式".to_string()),
+        };
+        Ok(Self { rows })
+    }
+
+    fn find_header(&self, accept: impl Fn(&[String]) -> bool) -> Option<usize> {
+        self.rows
+            .iter()
+            .take(Self::HEADER_SCAN_ROWS)
+            .position(|row| accept(row))
+    }
+
+    /// 表头及之前的全部文字，用于按机构名称嗅探
+    fn preamble(&self, header: usize) -> String {
+        self.rows
+            .iter()
+            .take(header + 1)
+            .map(|row| row.join(" "))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 银行导出多为 GBK 编码，不是合法 UTF-8 时按 GB18030 解码
+fn decode_bill_text(bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(text) => text.trim_start_matches('\u{feff}').to_string(),
+        Err(e) => encoding_rs::GB18030.decode(e.as_bytes()).0.into_owned(),
+    }
+}
 
-    for result in reader.records() {
-        let record = result.map_err(|_| "解析CSV失败".to_string())?;
-        if record.len() < 7 {
-            continue;
+fn cell_to_text(cell: &DataType) -> String {
+    match cell {
+        DataType::DateTime(_) | DataType::DateTimeIso(_) => cell_to_datetime(cell)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default(),
+        // 保留全部小数：时间列可能是未设格式的序列日期，小数部分是时分秒
+        DataType::Float(v) => v.to_string(),
+        _ => cell_to_string(cell).trim().to_string(),
+    }
+}
+
+/// 按 `names` 的先后优先取列，而不是按列在表头中的位置
+fn first_column(header: &[String], names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| find_column(header, &[name]))
+}
+
+fn has_column(header: &[String], names: &[&str]) -> bool {
+    find_column(header, names).is_some()
+}
+
+/// 按表头解析出的列位置，各格式共用同一套逐行解析逻辑
+#[derive(Debug, Default)]
+struct ColumnMap {
+    source: String,
+    time: Option<usize>,
+    /// 日期与时间分两列时的时间列
+    clock: Option<usize>,
+    partner: Option<usize>,
+    /// 交易对方为空时改用的列，如工行的“交易场所”
+    partner_fallback: Option<usize>,
+    product: Option<usize>,
+    /// “收/支”方向列；没有时由金额正负或收入/支出列判断
+    direction: Option<usize>,
+    amount: Option<usize>,
+    income: Option<usize>,
+    expense: Option<usize>,
+    /// 金额列中正数表示支出（信用卡账单），默认负数表示支出
+    positive_is_expense: bool,
+    category: Option<usize>,
+    payment_method: Option<usize>,
+    /// 没有支付方式列时填入的值，银行账单即卡本身
+    default_payment: String,
+    status: Option<usize>,
+    order_id: Option<usize>,
+}
+
+/// 银行账单没有“收/支”列，按资金方向与摘要判断：还款视为不计收支，收入中的退款单独记为退款
+fn bank_flow(incoming: bool, summary: &str) -> Option<BillFlow> {
+    if summary.contains("还款") {
+        return Some(BillFlow::Transfer);
+    }
+    BillFlow::classify(if incoming { "收入" } else { "支出" }, &[summary])
+}
+
+impl ColumnMap {
+    fn parse(&self, rows: &[Vec<String>]) -> Vec<BillEntry> {
+        let cell = |row: &[String], col: Option<usize>| -> String {
+            col.and_then(|c| row.get(c))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        let mut entries = Vec::new();
+        for row in rows {
+            if row.iter().all(|c| c.trim().is_empty()) {
+                continue;
+            }
+            let raw_time = match self.clock {
+                Some(_) => format!("{} {}", cell(row, self.time), cell(row, self.clock)),
+                None => cell(row, self.time),
+            };
+            let time = parse_bill_time(&raw_time);
+            // 合计、说明等非交易行
+            if time.is_none() && !raw_time.trim().is_empty() {
+                continue;
+            }
+
+            let partner = Some(cell(row, self.partner))
+                .filter(|p| !p.is_empty())
+                .unwrap_or_else(|| cell(row, self.partner_fallback));
+            let product = cell(row, self.product);
+            let platform_category = cell(row, self.category);
+            let status = cell(row, self.status);
+            let summary = format!("{} {}", product, partner);
+
+            let value = |col| parse_amount(&cell(row, col)).filter(|v| v.abs() > 0.0);
+            let parsed = if let Some(col) = self.direction {
+                // 有方向列时金额可来自金额列，或收入/支出列中有值的一列
+                let amount = match self.amount {
+                    Some(_) => parse_amount(&cell(row, self.amount)),
+                    None => value(self.expense).or_else(|| value(self.income)),
+                };
+                amount.and_then(|v| {
+                    BillFlow::classify(
+                        &cell(row, Some(col)),
+                        &[&platform_category, &status, &product],
+                    )
+                    .map(|flow| (v.abs(), flow))
+                })
+            } else if self.amount.is_some() {
+                parse_amount(&cell(row, self.amount)).and_then(|v| {
+                    let incoming = if self.positive_is_expense {
+                        v < 0.0
+                    } else {
+                        v > 0.0
+                    };
+                    bank_flow(incoming, &summary).map(|flow| (v.abs(), flow))
+                })
+            } else {
+                match (value(self.income), value(self.expense)) {
+                    (_, Some(v)) => bank_flow(false, &summary).map(|flow| (v.abs(), flow)),
+                    (Some(v), None) => bank_flow(true, &summary).map(|flow| (v.abs(), flow)),
+                    (None, None) => None,
+                }
+            };
+            let Some((amount, flow)) = parsed.filter(|(amount, _)| *amount > 0.0) else {
+                continue;
+            };
+
+            entries.push(BillEntry {
+                source: self.source.clone(),
+                time,
+                partner,
+                product,
+                amount,
+                category: String::new(),
+                platform_category,
+                payment_method: Some(cell(row, self.payment_method))
+                    .filter(|m| !m.is_empty())
+                    .unwrap_or_else(|| self.default_payment.clone()),
+                status,
+                flow,
+                refunded: 0.0,
+                order_id: cell(row, self.order_id),
+                refund_of: String::new(),
+            });
         }
+        entries
+    }
+}
 
-        let flow = record.get(5).unwrap_or("").trim();
-        let amount_text = record.get(6).unwrap_or("").trim();
+/// 一种账单导出格式：先嗅探内容找到表头，再给出各列位置
+trait BillParser {
+    fn name(&self) -> String;
+    /// 是本格式时返回表头所在行
+    fn sniff(&self, table: &BillTable) -> Option<usize>;
+    fn columns(&self, table: &BillTable, header: usize) -> ColumnMap;
 
-        if flow.is_empty() || amount_text.is_empty() {
-            continue;
+    fn parse(&self, table: &BillTable, header: usize) -> Vec<BillEntry> {
+        self.columns(table, header).parse(&table.rows[header + 1..])
+    }
+}
+
+/// 微信支付账单（Excel 与新版 CSV）
+struct WechatParser;
+
+impl BillParser for WechatParser {
+    fn name(&self) -> String {
+        "微信".into()
+    }
+
+    fn sniff(&self, table: &BillTable) -> Option<usize> {
+        table.find_header(|row| {
+            row.first().is_some_and(|c| c == "交易时间")
+                && has_column(row, &["交易单号", "当前状态"])
+                && !has_column(row, &["交易订单号"])
+        })
+    }
+
+    fn columns(&self, table: &BillTable, header: usize) -> ColumnMap {
+        let h = &table.rows[header];
+        ColumnMap {
+            source: self.name(),
+            time: Some(0),
+            category: Some(column_or(h, &["交易类型"], 1)),
+            partner: Some(column_or(h, &["交易对方"], 2)),
+            product: Some(column_or(h, &["商品"], 3)),
+            direction: Some(column_or(h, &["收/支"], 4)),
+            amount: Some(column_or(h, &["金额(元)", "金额"], 5)),
+            payment_method: Some(column_or(h, &["支付方式"], 6)),
+            status: Some(column_or(h, &["当前状态"], 7)),
+            order_id: Some(column_or(h, &["交易单号"], 8)),
+            ..ColumnMap::default()
+        }
+    }
+}
+
+/// 支付宝交易明细 CSV
+struct AlipayParser;
+
+impl BillParser for AlipayParser {
+    fn name(&self) -> String {
+        "支付宝".into()
+    }
+
+    fn sniff(&self, table: &BillTable) -> Option<usize> {
+        table.find_header(|row| row.first().is_some_and(|c| c == "交易时间"))
+    }
+
+    fn columns(&self, table: &BillTable, header: usize) -> ColumnMap {
+        let h = &table.rows[header];
+        ColumnMap {
+            source: self.name(),
+            time: Some(0),
+            category: Some(column_or(h, &["交易分类"], 1)),
+            partner: Some(column_or(h, &["交易对方"], 2)),
+            product: Some(column_or(h, &["商品说明"], 4)),
+            direction: Some(column_or(h, &["收/支"], 5)),
+            amount: Some(column_or(h, &["金额"], 6)),
+            payment_method: Some(column_or(h, &["收/付款方式"], 7)),
+            status: Some(column_or(h, &["交易状态"], 8)),
+            order_id: Some(column_or(h, &["交易订单号"], 9)),
+            ..ColumnMap::default()
         }
+    }
+}
 
-        let amount = match parse_amount(amount_text) {
-            Some(v) => v,
-            None => continue,
-        };
+/// 招商银行：储蓄卡交易明细（收入/支出列或带符号的交易金额）与信用卡账单（人民币金额，正数为消费）
+struct CmbParser;
 
-        let entry = BillEntry {
-            partner: record.get(2).unwrap_or("").trim().to_string(),
-            product: record.get(4).unwrap_or("").trim().to_string(),
-            amount,
+impl CmbParser {
+    fn is_credit(header: &[String]) -> bool {
+        has_column(header, &["人民币金额"])
+    }
+}
+
+impl BillParser for CmbParser {
+    fn name(&self) -> String {
+        "招商银行".into()
+    }
+
+    fn sniff(&self, table: &BillTable) -> Option<usize> {
+        let header = table.find_header(|row| {
+            (has_column(row, &["交易日期", "记账日期"])
+                && (has_column(row, &["交易备注", "联机余额"])
+                    || has_column(row, &["收入"]) && has_column(row, &["支出"])
+                    || has_column(row, &["交易金额"])))
+                || (has_column(row, &["交易日"]) && Self::is_credit(row))
+        })?;
+        let row = &table.rows[header];
+        let distinctive = has_column(row, &["交易备注", "联机余额"]) || Self::is_credit(row);
+        (distinctive || table.preamble(header).contains("招商银行")).then_some(header)
+    }
+
+    fn columns(&self, table: &BillTable, header: usize) -> ColumnMap {
+        let h = &table.rows[header];
+        let credit = Self::is_credit(h);
+        let source = if credit {
+            "招商银行信用卡".to_string()
+        } else {
+            self.name()
         };
+        let partner = first_column(h, &["对手信息", "交易备注", "交易摘要"]);
+        let product = first_column(h, &["交易摘要", "交易类型"]).filter(|c| Some(*c) != partner);
+        // 卡号末四位写进支付方式，便于在 [accounts.payment] 中区分不同的卡
+        let card = find_column(h, &["卡号末四位"]);
+        ColumnMap {
+            default_payment: source.clone(),
+            source,
+            time: find_column(h, &["交易日期", "交易日", "记账日期"]),
+            clock: find_column(h, &["交易时间"]),
+            partner,
+            product,
+            amount: find_column(h, &["交易金额", "人民币金额"]),
+            income: find_column(h, &["收入"]),
+            expense: find_column(h, &["支出"]),
+            positive_is_expense: credit,
+            payment_method: card,
+            ..ColumnMap::default()
+        }
+    }
+
+    fn parse(&self, table: &BillTable, header: usize) -> Vec<BillEntry> {
+        let map = self.columns(table, header);
+        let mut entries = map.parse(&table.rows[header + 1..]);
+        if map.payment_method.is_some() {
+            for e in &mut entries {
+                e.payment_method = format!("{}({})", map.source, e.payment_method);
+            }
+        }
+        entries
+    }
+}
+
+/// 工商银行借记卡明细：分列的收入 / 支出金额
+struct IcbcParser;
+
+const ICBC_INCOME: [&str; 4] = [
+    "记账金额(收入)",
+    "记账金额（收入）",
+    "交易金额(收入)",
+    "交易金额（收入）",
+];
+const ICBC_EXPENSE: [&str; 4] = [
+    "记账金额(支出)",
+    "记账金额（支出）",
+    "交易金额(支出)",
+    "交易金额（支出）",
+];
+
+impl BillParser for IcbcParser {
+    fn name(&self) -> String {
+        "工商银行".into()
+    }
+
+    fn sniff(&self, table: &BillTable) -> Option<usize> {
+        table.find_header(|row| {
+            has_column(row, &["交易日期"])
+                && has_column(row, &["摘要"])
+                && (has_column(row, &ICBC_INCOME) || has_column(row, &["对方户名"]))
+        })
+    }
+
+    fn columns(&self, table: &BillTable, header: usize) -> ColumnMap {
+        let h = &table.rows[header];
+        ColumnMap {
+            source: self.name(),
+            default_payment: self.name(),
+            time: find_column(h, &["交易日期"]),
+            partner: find_column(h, &["对方户名"]),
+            partner_fallback: find_column(h, &["交易场所"]),
+            product: find_column(h, &["摘要"]),
+            income: find_column(h, &ICBC_INCOME),
+            expense: find_column(h, &ICBC_EXPENSE),
+            amount: find_column(h, &["交易金额", "记账金额"]),
+            ..ColumnMap::default()
+        }
+    }
+}
 
-        if flow.contains('支') {
-            expenses.push(entry);
-        } else if flow.contains('收') {
-            incomes.push(entry);
+/// config.toml 中 `[[bill_formats]]` 定义的列映射
+struct MappedParser<'a>(&'a BillFormatConfig);
+
+impl MappedParser<'_> {
+    fn column(header: &[String], name: &str) -> Option<usize> {
+        let name = name.trim().to_lowercase();
+        if name.is_empty() {
+            return None;
         }
+        find_column(header, &[name.as_str()])
     }
 
-    Ok(BillAggregate::from_entries(expenses, incomes))
+    fn mapped_names(&self) -> Vec<&str> {
+        let c = self.0;
+        [
+            &c.date,
+            &c.time,
+            &c.partner,
+            &c.product,
+            &c.amount,
+            &c.income,
+            &c.expense,
+            &c.direction,
+            &c.category,
+            &c.payment_method,
+            &c.status,
+            &c.order_id,
+        ]
+        .into_iter()
+        .map(|s| s.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .collect()
+    }
 }
 
-fn prompt_export_directory(default: &Path) -> io::Result<PathBuf> {
+impl BillParser for MappedParser<'_> {
+    fn name(&self) -> String {
+        if self.0.name.trim().is_empty() {
+            "自定义".into()
+        } else {
+            self.0.name.trim().to_string()
+        }
+    }
+
+    fn sniff(&self, table: &BillTable) -> Option<usize> {
+        let c = self.0;
+        let has_amount = !c.amount.trim().is_empty()
+            || !c.income.trim().is_empty() && !c.expense.trim().is_empty();
+        if c.date.trim().is_empty() || !has_amount {
+            return None;
+        }
+        let names = self.mapped_names();
+        let header =
+            table.find_header(|row| names.iter().all(|name| Self::column(row, name).is_some()))?;
+        let text = table.preamble(header);
+        c.detect
+            .iter()
+            .all(|d| text.contains(d.as_str()))
+            .then_some(header)
+    }
+
+    fn columns(&self, table: &BillTable, header: usize) -> ColumnMap {
+        let h = &table.rows[header];
+        let c = self.0;
+        ColumnMap {
+            source: self.name(),
+            default_payment: self.name(),
+            time: Self::column(h, &c.date),
+            clock: Self::column(h, &c.time),
+            partner: Self::column(h, &c.partner),
+            product: Self::column(h, &c.product),
+            direction: Self::column(h, &c.direction),
+            amount: Self::column(h, &c.amount),
+            income: Self::column(h, &c.income),
+            expense: Self::column(h, &c.expense),
+            positive_is_expense: c.positive_is_expense,
+            category: Self::column(h, &c.category),
+            payment_method: Self::column(h, &c.payment_method),
+            status: Self::column(h, &c.status),
+            order_id: Self::column(h, &c.order_id),
+            ..ColumnMap::default()
+        }
+    }
+}
+
+/// 自定义格式优先，其次是内置的平台与银行格式
+fn bill_parsers(custom: &[BillFormatConfig]) -> Vec<Box<dyn BillParser + '_>> {
+    let mut parsers: Vec<Box<dyn BillParser + '_>> = custom
+        .iter()
+        .map(|c| Box::new(MappedParser(c)) as Box<dyn BillParser>)
+        .collect();
+    parsers.push(Box::new(WechatParser));
+    parsers.push(Box::new(AlipayParser));
+    parsers.push(Box::new(CmbParser));
+    parsers.push(Box::new(IcbcParser));
+    parsers
+}
+
+/// 按内容识别账单格式并解析，返回格式名称与交易
+fn analyze_bill_file(
+    path: &Path,
+    custom: &[BillFormatConfig],
+) -> Result<(String, BillAggregate), String> {
+    let table = BillTable::load(path)?;
+    bill_parsers(custom)
+        .into_iter()
+        .find_map(|parser| {
+            parser.sniff(&table).map(|header| {
+                (
+                    parser.name(),
+                    BillAggregate::from_entries(parser.parse(&table, header)),
+                )
+            })
+        })
+        .ok_or_else(|| "未找到账单列表".to_string())
+}
+
+/// 按表头名查找列，找不到时退回平台导出格式中的默认位置
+fn column_or(header: &[String], names: &[&str], fallback: usize) -> usize {
+    find_column(header, names).unwrap_or(fallback)
+}
+
+/// 临时退出 TUI，在终端中读取一行输入
+fn prompt_line(question: &str) -> io::Result<String> {
     execute!(std::io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
     disable_raw_mode()?;
 
     let result = (|| {
-        println!("请输入导出目录 (回车使用默认: {}):", default.display());
+        println!("{}", question);
         print!("> ");
         std::io::stdout().flush()?;
         let mut input = String::new();
         io::stdin().read_line(&mut input)?;
-        let trimmed = input.trim();
-        let mut target = if trimmed.is_empty() {
-            default.to_path_buf()
-        } else {
-            PathBuf::from(trimmed)
-        };
-        if target.is_relative() {
-            target = std::env::current_dir()?.join(target);
-        }
-        fs::create_dir_all(&target)?;
-        Ok(target)
+        Ok(input.trim().to_string())
     })();
 
     enable_raw_mode().ok();
@@ -728,12 +4649,30 @@ fn prompt_export_directory(default: &Path) -> io::Result<PathBuf> {
     result
 }
 
+fn prompt_export_directory(default: &Path) -> io::Result<PathBuf> {
+    let input = prompt_line(&format!(
+        "请输入导出目录 (回车使用默认: {}):",
+        default.display()
+    ))?;
+    let mut target = if input.is_empty() {
+        default.to_path_buf()
+    } else {
+        PathBuf::from(input)
+    };
+    if target.is_relative() {
+        target = std::env::current_dir()?.join(target);
+    }
+    fs::create_dir_all(&target)?;
+    Ok(target)
+}
+
 // ---------------- Table rendering ----------------
 fn render_table_generic(
     f: &mut Frame,
     area: Rect,
     rows: &[Vec<String>],
     scroll: usize,
+    selected: Option<usize>,
     title: &str,
 ) {
     if rows.is_empty() {
@@ -773,7 +4712,17 @@ fn render_table_generic(
         .collect();
 
     let h = area.height.saturating_sub(2) as usize;
-    let start = scroll.min(rows.len());
+    let mut start = scroll.min(rows.len());
+    // 保证选中行始终可见
+    if let Some(sel) = selected {
+        if sel < start {
+            start = sel;
+        } else if h > 0 && sel >= start + h {
+            start = sel + 1 - h;
+        }
+    }
+    // 选中行越界时也不能切出表格范围
+    start = start.min(rows.len().saturating_sub(h));
     let mut end = start.saturating_add(h);
     if end > rows.len() {
         end = rows.len();
@@ -788,13 +4737,16 @@ fn render_table_generic(
             for c in 0..cols {
                 cells.push(Cell::from(row.get(c).map(|s| s.as_str()).unwrap_or("")));
             }
-            let style = if start + i == 0 {
+            let mut style = if start + i == 0 {
                 Style::default()
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD)
             } else {
                 Style::default()
             };
+            if selected == Some(start + i) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
             Row::new(cells).style(style)
         })
         .collect();
@@ -804,15 +4756,14 @@ fn render_table_generic(
     f.render_widget(table, area);
 }
 
-fn render_table_page(
+/// 绘制页面的标题栏与帮助栏，返回中间的内容区域
+fn render_page_frame(
     f: &mut Frame,
     size: Rect,
     header_text: &str,
     block_title: &str,
-    table_title: &str,
-    rows: &[Vec<String>],
-    scroll: usize,
-) {
+    help_text: &str,
+) -> Rect {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -832,12 +4783,149 @@ fn render_table_page(
         .block(Block::default().borders(Borders::ALL).title(block_title));
     f.render_widget(header, chunks[0]);
 
-    render_table_generic(f, chunks[1], rows, scroll, table_title);
-
-    let help = Paragraph::new("jk -- move | q -- back | e -- edit | r -- refresh")
+    let help = Paragraph::new(help_text)
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL).title("Help"));
     f.render_widget(help, chunks[2]);
+
+    chunks[1]
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_table_page(
+    f: &mut Frame,
+    size: Rect,
+    header_text: &str,
+    block_title: &str,
+    table_title: &str,
+    rows: &[Vec<String>],
+    page: &TablePage,
+    help_text: &str,
+) {
+    let body = render_page_frame(f, size, header_text, block_title, help_text);
+    render_table_generic(f, body, rows, page.scroll, Some(page.selected), table_title);
+}
+
+fn render_category_pane(
+    f: &mut Frame,
+    area: Rect,
+    categories: &[(String, usize)],
+    total: usize,
+    selected: usize,
+    focused: bool,
+) {
+    let mut items = vec![ListItem::new(format!("全部 ({})", total))];
+    items.extend(
+        categories
+            .iter()
+            .map(|(name, n)| ListItem::new(format!("{} ({})", name, n))),
+    );
+    let border = if focused {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border)
+                .title("分类"),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("→ ");
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+fn render_link_picker(f: &mut Frame, area: Rect, picker: &LinkPicker) {
+    let title = match picker.action {
+        LinkAction::Open => "选择要打开的链接",
+        LinkAction::Copy => "选择要复制的链接",
+    };
+    let width = picker
+        .urls
+        .iter()
+        .map(|u| u.chars().count() as u16 + 6)
+        .max()
+        .unwrap_or(20)
+        .max(30);
+    let popup = centered_rect(area, width, picker.urls.len() as u16 + 2);
+    let items: Vec<ListItem> = picker
+        .urls
+        .iter()
+        .map(|u| ListItem::new(u.as_str()))
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(
+            Style::default()
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("→ ");
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+    f.render_widget(ClearWidget, popup);
+    f.render_stateful_widget(list, popup, &mut state);
+}
+
+fn render_meta_review(
+    f: &mut Frame,
+    area: Rect,
+    header: &[String],
+    suggestion: &MetaSuggestion,
+    left: usize,
+) {
+    let mut lines = vec![
+        Line::from(Span::styled(
+            suggestion.url.clone(),
+            Style::default().fg(Color::Cyan),
+        )),
+        Line::default(),
+    ];
+    for (col, value) in &suggestion.fills {
+        let name = header.get(*col).map(|h| h.as_str()).unwrap_or("");
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{}: ", name),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(value.clone()),
+        ]));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from("y -- 填入 | n -- 跳过 | Esc -- 结束"));
+
+    let popup = centered_rect(
+        area,
+        area.width.saturating_sub(10).min(90),
+        lines.len() as u16 + 6,
+    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("补全资源信息 (剩余 {})", left));
+    f.render_widget(ClearWidget, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(block).wrap(Wrap { trim: true }),
+        popup,
+    );
 }
 
 fn render_weather_panel(f: &mut Frame, area: Rect, cards: &[WeatherCard]) {
@@ -854,7 +4942,7 @@ fn render_weather_panel(f: &mut Frame, area: Rect, cards: &[WeatherCard]) {
     for (idx, card) in cards.iter().enumerate() {
         match card {
             WeatherCard::Success { name, forecasts } => {
-                for (idx, fc) in forecasts.iter().enumerate() {
+                for (idx, fc) in forecasts.iter().take(MENU_FORECAST_DAYS).enumerate() {
                     let title = if idx == 0 { name.as_str() } else { "  " };
                     lines.push(Line::from(vec![
                         Span::styled(
@@ -890,8 +4978,453 @@ fn render_weather_panel(f: &mut Frame, area: Rect, cards: &[WeatherCard]) {
         }
     }
 
-    let paragraph = Paragraph::new(lines).block(block).wrap(Wrap { trim: true });
-    f.render_widget(paragraph, area);
+    let paragraph = Paragraph::new(lines).block(block).wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
+}
+
+const AGENDA_DAYS: i64 = 8;
+/// 主菜单天气栏只显示最近几天，完整预报见议程页
+const MENU_FORECAST_DAYS: usize = 3;
+
+/// 今天及之后 7 天的日程：每天列出天气和当天截止的未完成任务，逾期任务归入今天
+fn agenda_lines(
+    rows: &[Vec<String>],
+    cards: &[WeatherCard],
+    today: NaiveDate,
+) -> Vec<Line<'static>> {
+    let header = rows.first();
+    let status_col = header.and_then(|h| find_column(h, TODO_STATUS_HEADERS));
+    let due_col = header.and_then(|h| find_column(h, TODO_DUE_HEADERS));
+    let tasks: Vec<(NaiveDate, String)> = (0..rows.len())
+        .filter(|&i| is_todo_row(rows, i))
+        .filter(|&i| {
+            !status_col
+                .and_then(|c| rows[i].get(c))
+                .is_some_and(|s| is_done_mark(s))
+        })
+        .filter_map(|i| {
+            let due = due_col
+                .and_then(|c| rows[i].get(c))
+                .and_then(|d| parse_date(d))?;
+            Some((due, todo_title(&rows[i])))
+        })
+        .collect();
+
+    let mut lines = Vec::new();
+    for offset in 0..AGENDA_DAYS {
+        let day = today + ChronoDuration::days(offset);
+        let mut title = format!("{} {}", day.format("%m-%d"), weekday_label(day));
+        if offset == 0 {
+            title.push_str(" 今天");
+        }
+        let mut spans = vec![Span::styled(
+            title,
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )];
+        for card in cards {
+            if let WeatherCard::Success { name, forecasts } = card {
+                match forecasts.iter().find(|fc| fc.date == Some(day)) {
+                    Some(fc) => spans.push(Span::raw(format!(
+                        "  {} {} {}",
+                        name, fc.condition, fc.temperature
+                    ))),
+                    // 超出套餐可预报天数
+                    None => spans.push(Span::styled(
+                        format!("  {} 无预报", name),
+                        Style::default().fg(Color::DarkGray),
+                    )),
+                }
+            }
+        }
+        lines.push(Line::from(spans));
+
+        let mut any = false;
+        for (due, task) in &tasks {
+            let overdue = offset == 0 && *due < today;
+            if *due == day || overdue {
+                any = true;
+                let style = if overdue {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default()
+                };
+                let text = if overdue {
+                    format!("  • {} (逾期 {})", task, due.format("%m-%d"))
+                } else {
+                    format!("  • {}", task)
+                };
+                lines.push(Line::from(Span::styled(text, style)));
+            }
+        }
+        if !any {
+            lines.push(Line::from(Span::styled(
+                "  无任务",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        lines.push(Line::default());
+    }
+    lines
+}
+
+fn render_agenda_view(
+    f: &mut Frame,
+    size: Rect,
+    rows: &[Vec<String>],
+    cards: &[WeatherCard],
+    scroll: usize,
+    last_msg: Option<&str>,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(3),
+        ])
+        .split(size);
+
+    let today = today();
+    let header = Paragraph::new(format!("{} 日程", today.format(DATE_FMT)))
+        .alignment(Alignment::Center)
+        .style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(Borders::ALL).title("Agenda"));
+    f.render_widget(header, chunks[0]);
+
+    let body = Paragraph::new(agenda_lines(rows, cards, today))
+        .scroll((scroll as u16, 0))
+        .block(Block::default().borders(Borders::ALL).title("未来一周"));
+    f.render_widget(body, chunks[1]);
+
+    let help = Paragraph::new(
+        last_msg.unwrap_or("jk -- move | w -- load weather | r -- refresh | q -- back"),
+    )
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL).title("Help"));
+    f.render_widget(help, chunks[2]);
+}
+
+/// 账单页汇总表：最近的周期排在最前，便于在窄屏中看到。
+fn period_view_rows(label: &str, periods: &[PeriodSummary]) -> Vec<Vec<String>> {
+    let mut rows = vec![vec![
+        label.to_string(),
+        "收入".to_string(),
+        "支出".to_string(),
+        "结余".to_string(),
+        "支出环比".to_string(),
+    ]];
+    rows.extend(period_rows(periods).into_iter().rev());
+    rows
+}
+
+const BILL_HELP: &str =
+    "a -- 分析 | o -- 导出 | r -- 刷新 | e -- 编辑分类规则 | c -- 重新分类 | m -- 跨平台去重 | Tab -- 切换 | q -- 返回";
+const TX_HELP: &str = "jk -- 移动 | Enter -- 详情 | s/S -- 排序/倒序 | / -- 搜索 | f -- 收支 | c -- 分类 | d -- 日期范围 | x -- 清除筛选 | Tab -- 切换 | q -- 返回";
+
+fn render_tx_browser(f: &mut Frame, area: Rect, bill_state: &BillState) {
+    let browser = &bill_state.browser;
+    let visible = browser.visible(&bill_state.aggregate);
+    let balance: f64 = visible
+        .iter()
+        .map(|e| match e.flow {
+            BillFlow::Expense => -e.net_amount(),
+            _ => e.net_amount(),
+        })
+        .sum();
+    let title = format!(
+        "交易 {} 笔 · 收支 {:+.2} 元 · {}",
+        visible.len(),
+        balance,
+        browser.describe()
+    );
+    let rows = TxBrowser::rows(&visible);
+    let current = browser.selected.min(visible.len().saturating_sub(1));
+    let selected = (!visible.is_empty()).then_some(current + 1);
+    render_table_generic(f, area, &rows, 0, selected, &title);
+
+    if browser.detail {
+        if let Some(entry) = visible.get(current) {
+            render_tx_detail(f, area, entry);
+        }
+    }
+}
+
+fn render_budgets(f: &mut Frame, area: Rect, statuses: &[BudgetStatus]) {
+    let title = format!("{} 月预算", today().format("%Y-%m"));
+    if statuses.is_empty() {
+        let msg =
+            Paragraph::new("未配置预算，在 config.toml 的 [budgets] 中添加，如 \"餐饮\" = 1500")
+                .alignment(Alignment::Center)
+                .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(msg, area);
+        return;
+    }
+
+    let outer = Block::default().borders(Borders::ALL).title(title);
+    let inner = outer.inner(area);
+    f.render_widget(outer, area);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            statuses
+                .iter()
+                .map(|_| Constraint::Length(3))
+                .chain(std::iter::once(Constraint::Min(0)))
+                .collect::<Vec<_>>(),
+        )
+        .split(inner);
+    for (st, row) in statuses.iter().zip(rows.iter()) {
+        let gauge = Gauge::default()
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(st.category.clone()),
+            )
+            .gauge_style(Style::default().fg(st.color()))
+            .ratio(st.ratio().clamp(0.0, 1.0))
+            .label(format!(
+                "{:.2} / {:.2} 元 ({:.0}%)",
+                st.spent,
+                st.limit,
+                st.ratio() * 100.0
+            ));
+        f.render_widget(gauge, *row);
+    }
+}
+
+const TOP_PARTNERS: usize = 10;
+
+/// 图表页：上方为分类支出条形图与支出最多的交易对方，下方为按月收支折线；随终端大小缩放
+fn render_bill_charts(f: &mut Frame, area: Rect, aggregate: &BillAggregate) {
+    if aggregate.is_empty() {
+        let msg = Paragraph::new("暂无数据，请先按a完成分析")
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL).title("图表"));
+        f.render_widget(msg, area);
+        return;
+    }
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let top = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(rows[0]);
+
+    // 分类支出：横向条形，分类名在左、金额在条上
+    let categories = aggregate.expense_by_category();
+    let bars: Vec<Bar> = categories
+        .iter()
+        .map(|c| {
+            Bar::default()
+                .value(c.amount.max(0.0).round() as u64)
+                .label(Line::from(c.category.clone()))
+                .text_value(format!("{:.0}", c.amount))
+        })
+        .collect();
+    let bar_chart = BarChart::default()
+        .block(Block::default().borders(Borders::ALL).title("分类支出"))
+        .direction(Direction::Horizontal)
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(1)
+        .bar_gap(0)
+        .bar_style(Style::default().fg(Color::Red))
+        .value_style(
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_widget(bar_chart, top[0]);
+
+    let mut partners = vec![vec![
+        "交易对方".to_string(),
+        "笔数".to_string(),
+        "支出".to_string(),
+    ]];
+    partners.extend(
+        aggregate
+            .top_partners(TOP_PARTNERS)
+            .into_iter()
+            .map(|(name, amount, count)| vec![name, count.to_string(), format!("{:.2}", amount)]),
+    );
+    render_table_generic(
+        f,
+        top[1],
+        &partners,
+        0,
+        None,
+        &format!("支出前 {}", TOP_PARTNERS),
+    );
+
+    // 按月收支折线，x 为月份序号
+    let monthly = aggregate.monthly();
+    let income: Vec<(f64, f64)> = monthly
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i as f64, p.income))
+        .collect();
+    let expense: Vec<(f64, f64)> = monthly
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i as f64, p.expense))
+        .collect();
+    let max_y = monthly
+        .iter()
+        .map(|p| p.income.max(p.expense))
+        .fold(0.0, f64::max)
+        .max(1.0)
+        * 1.1;
+    let max_x = (monthly.len().saturating_sub(1) as f64).max(1.0);
+    let x_labels: Vec<Span> = match monthly.len() {
+        0 => Vec::new(),
+        1 => vec![Span::raw(monthly[0].label.clone())],
+        n => vec![
+            Span::raw(monthly[0].label.clone()),
+            Span::raw(monthly[n / 2].label.clone()),
+            Span::raw(monthly[n - 1].label.clone()),
+        ],
+    };
+    let datasets = vec![
+        Dataset::default()
+            .name("收入")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Green))
+            .data(&income),
+        Dataset::default()
+            .name("支出")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Red))
+            .data(&expense),
+    ];
+    let chart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title("按月收支"))
+        .x_axis(
+            Axis::default()
+                .bounds([0.0, max_x])
+                .labels(x_labels)
+                .style(Style::default().fg(Color::Gray)),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max_y])
+                .labels(vec![
+                    Span::raw("0"),
+                    Span::raw(format!("{:.0}", max_y / 2.0)),
+                    Span::raw(format!("{:.0}", max_y)),
+                ])
+                .style(Style::default().fg(Color::Gray)),
+        );
+    f.render_widget(chart, rows[1]);
+}
+
+fn render_tx_detail(f: &mut Frame, area: Rect, e: &BillEntry) {
+    let mut fields = vec![
+        ("来源", e.source.clone()),
+        ("时间", format_bill_time(e.time.as_ref())),
+        ("交易对方", e.partner.clone()),
+        ("商品", e.product.clone()),
+        ("收支", e.flow.label().to_string()),
+        ("金额", format!("{:.2} 元", e.amount)),
+        ("分类", e.category.clone()),
+        ("平台分类", e.platform_category.clone()),
+        ("支付方式", e.payment_method.clone()),
+        ("交易状态", e.status.clone()),
+        ("订单号", e.order_id.clone()),
+    ];
+    match e.flow {
+        BillFlow::Expense if e.refunded > 0.0 => {
+            fields.push(("已退款", format!("{:.2} 元", e.refunded)))
+        }
+        BillFlow::Refund => fields.push((
+            "冲抵原交易",
+            if e.refunded > 0.0 {
+                format!("{:.2} 元", e.refunded)
+            } else {
+                "未匹配".to_string()
+            },
+        )),
+        _ => {}
+    }
+    let mut lines: Vec<Line> = fields
+        .into_iter()
+        .map(|(name, value)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{}: ", name),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(value),
+            ])
+        })
+        .collect();
+    lines.push(Line::default());
+    lines.push(Line::from("Enter / Esc -- 关闭"));
+
+    let popup = centered_rect(
+        area,
+        area.width.saturating_sub(10).min(80),
+        lines.len() as u16 + 2,
+    );
+    f.render_widget(ClearWidget, popup);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("交易详情"))
+            .wrap(Wrap { trim: true }),
+        popup,
+    );
+}
+
+fn render_pair_review(f: &mut Frame, area: Rect, pair: &BillPair, left: usize) {
+    let describe = |label: &str, e: &BillEntry| {
+        Line::from(vec![
+            Span::styled(
+                format!("{}（{}）: ", label, e.source),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                "{} | {} | {} | {:.2} 元",
+                format_bill_time(e.time.as_ref()),
+                e.partner,
+                e.product,
+                e.amount
+            )),
+        ])
+    };
+    let lines = vec![
+        describe("保留", &pair.keep),
+        describe("删除", &pair.drop),
+        Line::default(),
+        Line::from(Span::styled(
+            format!("交易对方相似度 {:.0}%", pair.similarity * 100.0),
+            Style::default().fg(Color::Cyan),
+        )),
+        Line::default(),
+        Line::from("y -- 合并 | n -- 不是同一笔 | Esc -- 结束"),
+    ];
+
+    let popup = centered_rect(
+        area,
+        area.width.saturating_sub(10).min(100),
+        lines.len() as u16 + 6,
+    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("跨平台重复 (剩余 {})", left));
+    f.render_widget(ClearWidget, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(block).wrap(Wrap { trim: true }),
+        popup,
+    );
 }
 
 fn render_bill_view(f: &mut Frame, size: Rect, bill_state: &BillState, last_msg: Option<&str>) {
@@ -904,7 +5437,17 @@ fn render_bill_view(f: &mut Frame, size: Rect, bill_state: &BillState, last_msg:
         ])
         .split(size);
 
-    let header = Paragraph::new("账单分析")
+    let tabs: Vec<String> = BillTab::ALL
+        .iter()
+        .map(|t| {
+            if *t == bill_state.tab {
+                format!("[{}]", t.title())
+            } else {
+                t.title().to_string()
+            }
+        })
+        .collect();
+    let header = Paragraph::new(format!("账单分析  {}", tabs.join("  ")))
         .alignment(Alignment::Center)
         .style(
             Style::default()
@@ -914,7 +5457,13 @@ fn render_bill_view(f: &mut Frame, size: Rect, bill_state: &BillState, last_msg:
         .block(Block::default().borders(Borders::ALL).title("Bill"));
     f.render_widget(header, chunks[0]);
 
-    if bill_state.files.is_empty() {
+    if bill_state.tab == BillTab::Transactions {
+        render_tx_browser(f, chunks[1], bill_state);
+    } else if bill_state.tab == BillTab::Charts {
+        render_bill_charts(f, chunks[1], &bill_state.aggregate);
+    } else if bill_state.tab == BillTab::Budgets {
+        render_budgets(f, chunks[1], &bill_state.budgets(today()));
+    } else if bill_state.files.is_empty() && bill_state.aggregate.is_empty() {
         let info_block = Paragraph::new("暂无账单")
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL).title("状态"));
@@ -923,8 +5472,28 @@ fn render_bill_view(f: &mut Frame, size: Rect, bill_state: &BillState, last_msg:
         let mut info_lines = vec![
             format!("账单目录: {}", bill_state.bill_dir.display()),
             format!("待分析账单: {}", bill_state.pending_count()),
-            format!("已分析账单: {}", bill_state.processed.len()),
+            format!("已导入账单: {}", bill_state.imported.len()),
+            format!("账本: {} 笔交易", bill_state.aggregate.entries.len()),
         ];
+        if bill_state.duplicates > 0 {
+            info_lines.push(format!("重复交易已去除: {} 笔", bill_state.duplicates));
+        }
+        if bill_state.merged_pairs > 0 {
+            info_lines.push(format!("已合并跨平台重复: {} 对", bill_state.merged_pairs));
+        }
+        if bill_state.unmatched_refunds > 0 {
+            info_lines.push(format!(
+                "未匹配原交易的退款: {} 笔（不计入统计）",
+                bill_state.unmatched_refunds
+            ));
+        }
+        if bill_state.excluded > 0 {
+            info_lines.push(format!(
+                "按状态排除: {} 笔（{}）",
+                bill_state.excluded,
+                bill_state.exclude_statuses.join("、")
+            ));
+        }
 
         if !bill_state.aggregate.is_empty() {
             let net = bill_state.aggregate.net();
@@ -935,13 +5504,79 @@ fn render_bill_view(f: &mut Frame, size: Rect, bill_state: &BillState, last_msg:
         let info_block = Paragraph::new(info_lines.join("\n"))
             .alignment(Alignment::Left)
             .block(Block::default().borders(Borders::ALL).title("状态"));
-        f.render_widget(info_block, chunks[1]);
+
+        // 状态 / 本次分析结果 / 汇总表，按是否有内容决定占用的行
+        let mut constraints = vec![Constraint::Length(info_lines.len() as u16 + 2)];
+        let has_results = !bill_state.results.is_empty();
+        let has_tables = !bill_state.aggregate.is_empty();
+        if has_results {
+            constraints.push(if has_tables {
+                Constraint::Length((bill_state.results.len() as u16 + 3).min(10))
+            } else {
+                Constraint::Min(4)
+            });
+        }
+        if has_tables {
+            constraints.push(Constraint::Min(5));
+        }
+        let body = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(chunks[1]);
+        f.render_widget(info_block, body[0]);
+
+        if has_results {
+            let mut rows = vec![vec![
+                "文件".to_string(),
+                "结果".to_string(),
+                "说明".to_string(),
+            ]];
+            rows.extend(bill_state.results.iter().map(|r| {
+                vec![
+                    r.name.clone(),
+                    if r.ok { "成功" } else { "失败" }.to_string(),
+                    r.detail.clone(),
+                ]
+            }));
+            render_table_generic(f, body[1], &rows, 0, None, "本次分析");
+        }
+
+        if has_tables {
+            let tables = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(30),
+                    Constraint::Percentage(35),
+                    Constraint::Percentage(35),
+                ])
+                .split(body[body.len() - 1]);
+            let aggregate = &bill_state.aggregate;
+            let mut categories = vec![vec![
+                "分类".to_string(),
+                "笔数".to_string(),
+                "支出".to_string(),
+                "占比".to_string(),
+            ]];
+            categories.extend(category_rows(
+                &aggregate.expense_by_category(),
+                aggregate.total_expense(),
+            ));
+            render_table_generic(f, tables[0], &categories, 0, None, "支出分类");
+            let monthly = period_view_rows("月份", &aggregate.monthly());
+            render_table_generic(f, tables[1], &monthly, 0, None, "按月汇总");
+            let weekly = period_view_rows("周", &aggregate.weekly());
+            render_table_generic(f, tables[2], &weekly, 0, None, "按周汇总");
+        }
     }
 
-    let help_text = if let Some(msg) = last_msg {
-        format!("a -- 分析 | o -- 导出 | r -- 刷新 | q -- 返回\n{}", msg)
-    } else {
-        "a -- 分析 | o -- 导出 | r -- 刷新 | q -- 返回".to_string()
+    let keys = match bill_state.tab {
+        BillTab::Summary => BILL_HELP,
+        BillTab::Transactions => TX_HELP,
+        BillTab::Charts | BillTab::Budgets => "Tab -- 切换 | q -- 返回",
+    };
+    let help_text = match last_msg {
+        Some(msg) => format!("{}\n{}", keys, msg),
+        None => keys.to_string(),
     };
 
     let help = Paragraph::new(help_text)
@@ -977,6 +5612,8 @@ enum AppState {
     TodoView,
     CyberView,
     BillView,
+    AgendaView,
+    PageView(usize),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -984,18 +5621,26 @@ enum MenuItem {
     Todo,
     Bill,
     Cyber,
+    Page(usize),
+    Agenda,
 }
 
 impl MenuItem {
-    fn all() -> [MenuItem; 3] {
-        [MenuItem::Todo, MenuItem::Bill, MenuItem::Cyber]
+    fn all(cfg: &Config) -> Vec<MenuItem> {
+        let mut items = vec![MenuItem::Todo, MenuItem::Bill, MenuItem::Cyber];
+        items.extend((0..cfg.pages.len()).map(MenuItem::Page));
+        // 新入口放在最后，不改变已有条目的位置
+        items.push(MenuItem::Agenda);
+        items
     }
 
-    fn title(&self) -> &'static str {
+    fn title<'a>(&self, cfg: &'a Config) -> &'a str {
         match self {
+            MenuItem::Agenda => "AGENDA",
             MenuItem::Todo => "TODO",
             MenuItem::Bill => "BILL",
             MenuItem::Cyber => "CYBER RESOURCE",
+            MenuItem::Page(i) => cfg.pages[*i].title.as_str(),
         }
     }
 }
@@ -1005,20 +5650,38 @@ fn run_app(
     cfg: &Config,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut selected = 0usize;
-    let items = MenuItem::all();
+    let items = MenuItem::all(cfg);
     let mut last_msg: Option<String> = None;
     let mut list_state = ListState::default();
     list_state.select(Some(0));
     let mut state = AppState::MainMenu;
     let mut force_redraw = false;
 
-    let mut todo: Vec<Vec<String>> = Vec::new();
-    let mut todo_scroll = 0usize;
+    let mut todo = TablePage::new(&cfg.todo_file_path, None);
+    let mut todo_folded: HashSet<String> = HashSet::new();
+    let mut todo_fold_pending = false;
+    let mut pomodoro: Option<Pomodoro> = None;
+    let mut agenda_scroll = 0usize;
 
-    let mut cyber: Vec<Vec<String>> = Vec::new();
-    let mut cyber_scroll = 0usize;
+    let mut cyber = TablePage::new(&cfg.cyber_resource_file_path, None);
+    let mut pages: Vec<TablePage> = cfg
+        .pages
+        .iter()
+        .map(|p| TablePage::new(&p.path, p.table.as_deref()))
+        .collect();
+    let mut link_picker: Option<LinkPicker> = None;
+    let mut cyber_category = 0usize;
+    let mut cyber_focus_categories = false;
+    let mut link_statuses: HashMap<String, LinkStatus> = HashMap::new();
+    let (link_tx, link_rx) = mpsc::channel::<(Vec<(String, LinkStatus)>, io::Result<PathBuf>)>();
+    let mut meta_queue: VecDeque<MetaSuggestion> = VecDeque::new();
+    let (meta_tx, meta_rx) = mpsc::channel::<Vec<MetaSuggestion>>();
 
     let mut bill_state = BillState::new(cfg);
+    // 主菜单要显示本月预算，启动时先读入账本；出错时进入账单页再提示
+    if !cfg.budgets.is_empty() {
+        let _ = bill_state.load_ledger();
+    }
     let mut weather_cards = Vec::new(); // 初始化为空，按w再加载
 
     // 创建通道用于接收天气数据
@@ -1033,6 +5696,22 @@ fn run_app(
 
     loop {
         list_state.select(Some(selected));
+        if pomodoro.as_ref().is_some_and(|p| p.is_finished()) {
+            if let Some(p) = pomodoro.take() {
+                // 终端响铃提醒
+                print!("\x07");
+                io::stdout().flush().ok();
+                last_msg = Some(
+                    match record_todo_time(&cfg.todo_file_path, &p.task, p.length) {
+                        Ok(msg) => format!("番茄钟结束：{} | {}", p.task, msg),
+                        Err(e) => format!("番茄钟结束：{} | {}", p.task, e),
+                    },
+                );
+                if state == AppState::TodoView {
+                    todo.load();
+                }
+            }
+        }
         if force_redraw {
             terminal.clear()?;
             force_redraw = false;
@@ -1076,7 +5755,7 @@ fn run_app(
                                 Style::default()
                             };
                             ListItem::new(Line::from(Span::styled(
-                                format!(" {} : {} ", i, item.title()),
+                                format!(" {} : {} ", i, item.title(cfg)),
                                 style,
                             )))
                         })
@@ -1094,7 +5773,19 @@ fn run_app(
                         .direction(Direction::Horizontal)
                         .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
                         .split(chunks[1]);
-                    f.render_stateful_widget(list, body[0], &mut list_state);
+                    match budget_summary_line(&bill_state.budgets(today())) {
+                        Some(line) => {
+                            let left = Layout::default()
+                                .direction(Direction::Vertical)
+                                .constraints([Constraint::Min(3), Constraint::Length(3)])
+                                .split(body[0]);
+                            f.render_stateful_widget(list, left[0], &mut list_state);
+                            let budget = Paragraph::new(line)
+                                .block(Block::default().borders(Borders::ALL).title("Budget"));
+                            f.render_widget(budget, left[1]);
+                        }
+                        None => f.render_stateful_widget(list, body[0], &mut list_state),
+                    }
                     render_weather_panel(f, body[1], &weather_cards);
 
                     let help = match &last_msg {
@@ -1107,21 +5798,122 @@ fn run_app(
                     f.render_widget(footer, chunks[2]);
                 }
                 AppState::TodoView => {
-                    render_table_page(f, size, "TODO List", "TODO", "Tasks", &todo, todo_scroll);
+                    let help = match &last_msg {
+                        Some(m) => m.as_str(),
+                        None => {
+                            "jk -- move | x -- done | A -- archive | za/zM/zR -- fold | t/p/T -- timer | q -- back | e -- edit | r -- refresh"
+                        }
+                    };
+                    let header_text = match &pomodoro {
+                        Some(p) => format!("TODO List | {}", p.status_line()),
+                        None => "TODO List".to_string(),
+                    };
+                    let visible = todo_visible_rows(&todo.rows, &todo_folded);
+                    let display = todo_display_rows(&todo.rows, &visible, &todo_folded);
+                    render_table_page(
+                        f,
+                        size,
+                        &header_text,
+                        "TODO",
+                        "Tasks",
+                        &display,
+                        &todo,
+                        help,
+                    );
                 }
                 AppState::CyberView => {
-                    render_table_page(
+                    let body = render_page_frame(
                         f,
                         size,
                         "Cyber Resource List",
                         "Cyber Resource",
-                        "Resources",
-                        &cyber,
-                        cyber_scroll,
+                        last_msg.as_deref().unwrap_or(
+                            "jk -- move | hl -- pane | o -- open link | y -- copy link | c -- check links | i -- import bookmarks | m -- fetch info | q -- back | e -- edit | r -- refresh",
+                        ),
+                    );
+                    let display = cyber_display_rows(&cyber.rows, &link_statuses);
+                    let category_col = cyber_category_col(cfg, &cyber.rows);
+                    let table_area = match category_col {
+                        Some(col) => {
+                            let panes = Layout::default()
+                                .direction(Direction::Horizontal)
+                                .constraints([Constraint::Length(24), Constraint::Min(10)])
+                                .split(body);
+                            let categories = cyber_categories(&cyber.rows, col);
+                            let total = categories.iter().map(|(_, n)| n).sum();
+                            render_category_pane(
+                                f,
+                                panes[0],
+                                &categories,
+                                total,
+                                cyber_category,
+                                cyber_focus_categories,
+                            );
+                            panes[1]
+                        }
+                        None => body,
+                    };
+                    let categories = category_col
+                        .map(|col| cyber_categories(&cyber.rows, col))
+                        .unwrap_or_default();
+                    let current = cyber_category
+                        .checked_sub(1)
+                        .and_then(|i| categories.get(i))
+                        .map(|(c, _)| c.as_str());
+                    let visible: Vec<Vec<String>> =
+                        cyber_visible_rows(&cyber.rows, category_col, current)
+                            .into_iter()
+                            .map(|i| display[i].clone())
+                            .collect();
+                    render_table_generic(
+                        f,
+                        table_area,
+                        &visible,
+                        cyber.scroll,
+                        (!cyber_focus_categories).then_some(cyber.selected),
+                        current.unwrap_or("Resources"),
                     );
+                    if let Some(picker) = &link_picker {
+                        render_link_picker(f, size, picker);
+                    }
+                    if let (Some(header), Some(suggestion)) = (cyber.rows.first(), meta_queue.front()) {
+                        render_meta_review(f, size, header, suggestion, meta_queue.len());
+                    }
                 }
                 AppState::BillView => {
                     render_bill_view(f, size, &bill_state, last_msg.as_deref());
+                    if let Some(pair) = bill_state.pairs.front() {
+                        render_pair_review(f, size, pair, bill_state.pairs.len());
+                    }
+                }
+                AppState::PageView(i) => {
+                    let title = cfg.pages[i].title.as_str();
+                    let file_name = Path::new(&pages[i].path)
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or(title);
+                    render_table_page(
+                        f,
+                        size,
+                        title,
+                        title,
+                        file_name,
+                        &pages[i].rows,
+                        &pages[i],
+                        last_msg
+                            .as_deref()
+                            .unwrap_or("jk -- move | q -- back | e -- edit | r -- refresh"),
+                    );
+                }
+                AppState::AgendaView => {
+                    render_agenda_view(
+                        f,
+                        size,
+                        &todo.rows,
+                        &weather_cards,
+                        agenda_scroll,
+                        last_msg.as_deref(),
+                    );
                 }
             }
         })?;
@@ -1134,6 +5926,30 @@ fn run_app(
             }
         }
 
+        if let Ok((results, written)) = link_rx.try_recv() {
+            let broken = results.iter().filter(|(_, s)| s.is_broken()).count();
+            let report = match written {
+                Ok(path) => format!("报告：{}", path.display()),
+                Err(e) => format!("报告写入失败: {}", e),
+            };
+            last_msg = Some(format!(
+                "检查完成：{} 个链接，失效 {} 个，{}",
+                results.len(),
+                broken,
+                report
+            ));
+            link_statuses = results.into_iter().collect();
+        }
+
+        if let Ok(suggestions) = meta_rx.try_recv() {
+            last_msg = Some(if suggestions.is_empty() {
+                "没有可补全的资源".to_string()
+            } else {
+                format!("获取到 {} 条资源信息，请逐条确认", suggestions.len())
+            });
+            meta_queue = suggestions.into();
+        }
+
         if event::poll(std::time::Duration::from_millis(250))? {
             if let Event::Key(key) = event::read()? {
                 if key.kind == KeyEventKind::Press {
@@ -1153,21 +5969,32 @@ fn run_app(
                                 last_msg = None;
                             }
                             KeyCode::Enter => match items[selected] {
+                                MenuItem::Agenda => {
+                                    todo.load();
+                                    agenda_scroll = 0;
+                                    state = AppState::AgendaView;
+                                    last_msg = None;
+                                }
                                 MenuItem::Todo => {
-                                    load_table(&mut todo, &mut todo_scroll, read_todo, cfg);
+                                    todo.load();
                                     state = AppState::TodoView;
                                     last_msg = None;
                                 }
                                 MenuItem::Cyber => {
-                                    load_table(&mut cyber, &mut cyber_scroll, read_cyber, cfg);
+                                    cyber.load();
                                     state = AppState::CyberView;
                                     last_msg = None;
                                 }
+                                MenuItem::Page(i) => {
+                                    pages[i].load();
+                                    state = AppState::PageView(i);
+                                    last_msg = None;
+                                }
                                 MenuItem::Bill => {
                                     let _ = bill_state.refresh_files();
                                     state = AppState::BillView;
                                     force_redraw = true;
-                                    last_msg = None;
+                                    last_msg = bill_state.load_ledger().err();
                                 }
                             },
                             KeyCode::Char('w') => {
@@ -1176,63 +6003,388 @@ fn run_app(
                             }
                             _ => {}
                         },
-                        AppState::TodoView => match key.code {
-                            KeyCode::Char('q') => {
-                                state = AppState::MainMenu;
-                                todo_scroll = 0;
-                            }
-                            KeyCode::Char('e') => {
-                                edit_table(
-                                    &mut todo,
-                                    &mut todo_scroll,
-                                    read_todo,
-                                    &cfg.todo_file_path,
-                                    cfg,
-                                    &mut force_redraw,
-                                );
-                            }
-                            KeyCode::Char('r') => {
-                                load_table(&mut todo, &mut todo_scroll, read_todo, cfg);
+                        AppState::TodoView => {
+                            let visible = todo_visible_rows(&todo.rows, &todo_folded);
+                            let current = visible.get(todo.selected).copied().unwrap_or(0);
+                            let fold_key = std::mem::take(&mut todo_fold_pending);
+                            let fold_target = todo_fold_target(&todo.rows, current);
+                            match key.code {
+                                KeyCode::Char('z') if !fold_key => todo_fold_pending = true,
+                                KeyCode::Char(c @ ('a' | 'o' | 'c' | 'M' | 'R')) if fold_key => {
+                                    match (c, fold_target) {
+                                        ('M', _) => {
+                                            todo_folded = (0..todo.rows.len())
+                                                .filter(|&i| {
+                                                    is_todo_row(&todo.rows, i)
+                                                        && !todo_children(&todo.rows, i).is_empty()
+                                                })
+                                                .map(|i| todo_title(&todo.rows[i]))
+                                                .collect();
+                                        }
+                                        ('R', _) => todo_folded.clear(),
+                                        (_, Some(target)) => {
+                                            let title = todo_title(&todo.rows[target]);
+                                            let folded = todo_folded.contains(&title);
+                                            if c == 'c' || (c == 'a' && !folded) {
+                                                todo_folded.insert(title);
+                                            } else {
+                                                todo_folded.remove(&title);
+                                            }
+                                        }
+                                        _ => {}
+                                    }
+                                    // 折叠后光标停在被折叠的父任务上
+                                    let anchor = fold_target.unwrap_or(current);
+                                    let visible = todo_visible_rows(&todo.rows, &todo_folded);
+                                    todo.selected =
+                                        visible.iter().rposition(|&i| i <= anchor).unwrap_or(0);
+                                }
+                                KeyCode::Char('x') => {
+                                    last_msg =
+                                        Some(match complete_todo(&cfg.todo_file_path, current) {
+                                            Ok(msg) => msg,
+                                            Err(e) => e,
+                                        });
+                                    todo.load();
+                                }
+                                KeyCode::Char('t') => {
+                                    if !is_todo_row(&todo.rows, current) {
+                                        last_msg = Some("请选择任务行".into());
+                                    } else {
+                                        let mut msgs = Vec::new();
+                                        if let Some(p) = pomodoro.take() {
+                                            if let Ok(m) = record_todo_time(
+                                                &cfg.todo_file_path,
+                                                &p.task,
+                                                p.elapsed(),
+                                            ) {
+                                                msgs.push(format!("{}：{}", p.task, m));
+                                            }
+                                        }
+                                        let task = todo_title(&todo.rows[current]);
+                                        msgs.push(format!("开始计时：{}", task));
+                                        pomodoro =
+                                            Some(Pomodoro::start(task, cfg.pomodoro_minutes));
+                                        last_msg = Some(msgs.join(" | "));
+                                        todo.load();
+                                    }
+                                }
+                                KeyCode::Char('p') => match pomodoro.as_mut() {
+                                    Some(p) => p.toggle_pause(),
+                                    None => last_msg = Some("没有进行中的计时".into()),
+                                },
+                                KeyCode::Char('T') => match pomodoro.take() {
+                                    Some(p) => {
+                                        last_msg = Some(
+                                            match record_todo_time(
+                                                &cfg.todo_file_path,
+                                                &p.task,
+                                                p.elapsed(),
+                                            ) {
+                                                Ok(m) => format!("停止计时：{} | {}", p.task, m),
+                                                Err(e) => format!("停止计时：{} | {}", p.task, e),
+                                            },
+                                        );
+                                        todo.load();
+                                    }
+                                    None => last_msg = Some("没有进行中的计时".into()),
+                                },
+                                KeyCode::Char('A') => {
+                                    let archive = cfg.todo_archive_path();
+                                    last_msg = Some(
+                                        match archive_done_todos(&cfg.todo_file_path, &archive) {
+                                            Ok(n) => {
+                                                format!(
+                                                    "已归档 {} 条任务至 {}",
+                                                    n,
+                                                    archive.display()
+                                                )
+                                            }
+                                            Err(e) => e,
+                                        },
+                                    );
+                                    todo.load();
+                                    todo.selected = 0;
+                                }
+                                code => {
+                                    if todo.handle_key(code, visible.len(), &mut force_redraw)
+                                        == TableKey::Back
+                                    {
+                                        state = AppState::MainMenu;
+                                        last_msg = None;
+                                    }
+                                }
                             }
-                            KeyCode::Char('k') => {
-                                scroll_up(&mut todo_scroll);
+                        }
+                        AppState::CyberView if !meta_queue.is_empty() => match key.code {
+                            KeyCode::Char('y') => {
+                                if let Some(suggestion) = meta_queue.pop_front() {
+                                    last_msg = Some(
+                                        match apply_meta_suggestion(
+                                            &cfg.cyber_resource_file_path,
+                                            &suggestion,
+                                        ) {
+                                            Ok(_) => format!("已补全 {}", suggestion.url),
+                                            Err(e) => e,
+                                        },
+                                    );
+                                    cyber.load();
+                                }
                             }
-                            KeyCode::Char('j') => {
-                                scroll_down(&mut todo_scroll, todo.len());
+                            KeyCode::Char('n') => {
+                                meta_queue.pop_front();
                             }
+                            KeyCode::Char('q') | KeyCode::Esc => meta_queue.clear(),
                             _ => {}
                         },
-                        AppState::CyberView => match key.code {
+                        AppState::CyberView if link_picker.is_some() => {
+                            let picker = link_picker.as_mut().expect("picker is open");
+                            match key.code {
+                                KeyCode::Char('q') | KeyCode::Esc => link_picker = None,
+                                KeyCode::Char('k') | KeyCode::Up => {
+                                    picker.selected = picker.selected.saturating_sub(1);
+                                }
+                                KeyCode::Char('j') | KeyCode::Down => {
+                                    if picker.selected + 1 < picker.urls.len() {
+                                        picker.selected += 1;
+                                    }
+                                }
+                                KeyCode::Enter => {
+                                    let url = &picker.urls[picker.selected];
+                                    last_msg = Some(run_link_action(cfg, picker.action, url));
+                                    link_picker = None;
+                                }
+                                _ => {}
+                            }
+                        }
+                        AppState::CyberView => {
+                            let category_col = cyber_category_col(cfg, &cyber.rows);
+                            let categories = category_col
+                                .map(|col| cyber_categories(&cyber.rows, col))
+                                .unwrap_or_default();
+                            if cyber_category > categories.len() {
+                                cyber_category = 0;
+                            }
+                            let visible = cyber_visible_rows(
+                                &cyber.rows,
+                                category_col,
+                                cyber_category
+                                    .checked_sub(1)
+                                    .and_then(|i| categories.get(i))
+                                    .map(|(c, _)| c.as_str()),
+                            );
+                            let current = visible.get(cyber.selected).copied().unwrap_or(0);
+                            match key.code {
+                                KeyCode::Char('h') if category_col.is_some() => {
+                                    cyber_focus_categories = true;
+                                }
+                                KeyCode::Char('l') | KeyCode::Enter if cyber_focus_categories => {
+                                    cyber_focus_categories = false;
+                                }
+                                KeyCode::Char('k') if cyber_focus_categories => {
+                                    cyber_category = cyber_category.saturating_sub(1);
+                                    cyber.selected = 0;
+                                    cyber.scroll = 0;
+                                }
+                                KeyCode::Char('j') if cyber_focus_categories => {
+                                    if cyber_category < categories.len() {
+                                        cyber_category += 1;
+                                    }
+                                    cyber.selected = 0;
+                                    cyber.scroll = 0;
+                                }
+                                KeyCode::Char('i') => {
+                                    match prompt_line(
+                                        "请输入书签文件路径 (Netscape HTML 或每行一个链接):",
+                                    ) {
+                                        Ok(input) if input.is_empty() => last_msg = None,
+                                        Ok(input) => {
+                                            last_msg = Some(
+                                                match import_bookmarks(cfg, Path::new(&input)) {
+                                                    Ok((added, skipped)) => format!(
+                                                        "已导入 {} 条，跳过重复 {} 条",
+                                                        added, skipped
+                                                    ),
+                                                    Err(e) => e,
+                                                },
+                                            );
+                                            cyber.load();
+                                        }
+                                        Err(e) => last_msg = Some(format!("输入路径失败: {}", e)),
+                                    }
+                                    force_redraw = true;
+                                }
+                                KeyCode::Char('m') => {
+                                    let rows = cyber.rows.clone();
+                                    let tx = meta_tx.clone();
+                                    let cfg = cfg.clone();
+                                    last_msg = Some("正在获取网页信息…".into());
+                                    thread::spawn(move || {
+                                        let _ = tx.send(collect_meta_suggestions(&cfg, &rows));
+                                    });
+                                }
+                                KeyCode::Char('c') => {
+                                    let rows = cyber.rows.clone();
+                                    let tx = link_tx.clone();
+                                    let cfg = cfg.clone();
+                                    last_msg = Some(format!(
+                                        "正在检查 {} 个链接…",
+                                        resource_urls(&rows).len()
+                                    ));
+                                    thread::spawn(move || {
+                                        let _ = tx.send(run_link_check(&cfg, &rows));
+                                    });
+                                }
+                                KeyCode::Char(c @ ('o' | 'y')) => {
+                                    let action = if c == 'o' {
+                                        LinkAction::Open
+                                    } else {
+                                        LinkAction::Copy
+                                    };
+                                    let urls = cyber
+                                        .rows
+                                        .get(current)
+                                        .map(|row| extract_urls(row))
+                                        .unwrap_or_default();
+                                    match urls.len() {
+                                        0 => last_msg = Some("当前行没有链接".into()),
+                                        1 => {
+                                            last_msg = Some(run_link_action(cfg, action, &urls[0]))
+                                        }
+                                        _ => {
+                                            link_picker = Some(LinkPicker {
+                                                urls,
+                                                selected: 0,
+                                                action,
+                                            })
+                                        }
+                                    }
+                                }
+                                code => {
+                                    if cyber.handle_key(code, visible.len(), &mut force_redraw)
+                                        == TableKey::Back
+                                    {
+                                        state = AppState::MainMenu;
+                                        cyber_category = 0;
+                                        cyber_focus_categories = false;
+                                        last_msg = None;
+                                    }
+                                }
+                            }
+                        }
+                        AppState::PageView(i) => {
+                            let page = &mut pages[i];
+                            let len = page.rows.len();
+                            if page.handle_key(key.code, len, &mut force_redraw) == TableKey::Back {
+                                state = AppState::MainMenu;
+                                last_msg = None;
+                            }
+                        }
+                        AppState::AgendaView => match key.code {
                             KeyCode::Char('q') => {
                                 state = AppState::MainMenu;
-                                cyber_scroll = 0;
+                                last_msg = None;
                             }
-                            KeyCode::Char('e') => {
-                                edit_table(
-                                    &mut cyber,
-                                    &mut cyber_scroll,
-                                    read_cyber,
-                                    &cfg.cyber_resource_file_path,
-                                    cfg,
-                                    &mut force_redraw,
-                                );
+                            KeyCode::Char('k') => {
+                                agenda_scroll = agenda_scroll.saturating_sub(1);
+                            }
+                            KeyCode::Char('j') => {
+                                let len = agenda_lines(&todo.rows, &weather_cards, today()).len();
+                                agenda_scroll = (agenda_scroll + 1).min(len.saturating_sub(1));
                             }
                             KeyCode::Char('r') => {
-                                load_table(&mut cyber, &mut cyber_scroll, read_cyber, cfg);
+                                todo.load();
                             }
-                            KeyCode::Char('k') => {
-                                scroll_up(&mut cyber_scroll);
+                            KeyCode::Char('w') => {
+                                fetch_weather_board(cfg.clone(), weather_tx.clone());
                             }
-                            KeyCode::Char('j') => {
-                                scroll_down(&mut cyber_scroll, cyber.len());
+                            _ => {}
+                        },
+                        AppState::BillView if !bill_state.pairs.is_empty() => match key.code {
+                            KeyCode::Char('y') => {
+                                if let Err(e) = bill_state.confirm_pair() {
+                                    last_msg = Some(e);
+                                }
+                            }
+                            KeyCode::Char('n') => {
+                                if let Err(e) = bill_state.reject_pair() {
+                                    last_msg = Some(e);
+                                }
+                            }
+                            KeyCode::Char('q') | KeyCode::Esc => {
+                                bill_state.pairs.clear();
+                                last_msg =
+                                    Some(format!("已合并 {} 对重复交易", bill_state.merged_pairs));
                             }
                             _ => {}
                         },
+                        AppState::BillView if bill_state.browser.detail => {
+                            let len = bill_state.browser.visible(&bill_state.aggregate).len();
+                            bill_state.browser.clamp_selection(len);
+                            if matches!(
+                                key.code,
+                                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q')
+                            ) {
+                                bill_state.browser.detail = false;
+                            }
+                        }
+                        AppState::BillView if bill_state.tab == BillTab::Transactions => {
+                            let len = bill_state.browser.visible(&bill_state.aggregate).len();
+                            let browser = &mut bill_state.browser;
+                            browser.clamp_selection(len);
+                            match key.code {
+                                KeyCode::Char('q') => {
+                                    state = AppState::MainMenu;
+                                    force_redraw = true;
+                                }
+                                KeyCode::Tab => bill_state.tab = bill_state.tab.next(),
+                                KeyCode::Char('k') | KeyCode::Up => {
+                                    browser.selected = browser.selected.saturating_sub(1);
+                                }
+                                KeyCode::Char('j') | KeyCode::Down => {
+                                    if browser.selected + 1 < len {
+                                        browser.selected += 1;
+                                    }
+                                }
+                                KeyCode::Enter => browser.detail = len > 0,
+                                KeyCode::Char('s') => browser.cycle_sort(),
+                                KeyCode::Char('S') => browser.toggle_order(),
+                                KeyCode::Char('f') => browser.cycle_flow(),
+                                KeyCode::Char('c') => browser.cycle_category(&bill_state.aggregate),
+                                KeyCode::Char('x') => browser.clear_filters(),
+                                KeyCode::Char('/') => {
+                                    match prompt_line("搜索交易对方/商品/分类/订单号（留空清除）：")
+                                    {
+                                        Ok(q) => {
+                                            browser.query = q;
+                                            browser.selected = 0;
+                                        }
+                                        Err(e) => last_msg = Some(format!("输入失败: {}", e)),
+                                    }
+                                    force_redraw = true;
+                                }
+                                KeyCode::Char('d') => {
+                                    match prompt_line(
+                                        "日期范围，如 2024-05-01~2024-05-31，可只写一端，留空清除：",
+                                    ) {
+                                        Ok(input) => {
+                                            if let Err(e) = browser.set_range(&input) {
+                                                last_msg = Some(e);
+                                            }
+                                        }
+                                        Err(e) => last_msg = Some(format!("输入失败: {}", e)),
+                                    }
+                                    force_redraw = true;
+                                }
+                                _ => {}
+                            }
+                        }
                         AppState::BillView => match key.code {
                             KeyCode::Char('q') => {
                                 state = AppState::MainMenu;
                                 force_redraw = true;
                             }
+                            KeyCode::Tab => bill_state.tab = bill_state.tab.next(),
                             KeyCode::Char('r') => {
                                 match bill_state.refresh_files() {
                                     Ok(_) => {
@@ -1261,27 +6413,96 @@ fn run_app(
                                             };
                                             format!("{}：{:.2} 元", label, net_val.abs())
                                         };
-                                        last_msg = Some(format!("完成 {} 份账单分析 | {}", n, net));
+                                        let mut dup = if bill_state.duplicates > 0 {
+                                            format!(" | 去除重复 {} 笔", bill_state.duplicates)
+                                        } else {
+                                            String::new()
+                                        };
+                                        let failed =
+                                            bill_state.results.iter().filter(|r| !r.ok).count();
+                                        if failed > 0 {
+                                            dup.push_str(&format!(" | {} 份失败", failed));
+                                        }
+                                        last_msg =
+                                            Some(format!("完成 {} 份账单分析{} | {}", n, dup, net));
                                     }
                                     Err(e) => last_msg = Some(e),
                                 }
                                 force_redraw = true;
                             }
+                            KeyCode::Char('e') => {
+                                if let Err(e) = bill_state.edit_rules() {
+                                    last_msg = Some(e);
+                                } else if !bill_state.aggregate.is_empty() {
+                                    last_msg = Some(match bill_state.reapply_rules() {
+                                        Ok(n) => format!("已重新分类，未分类支出 {} 笔", n),
+                                        Err(e) => e,
+                                    });
+                                }
+                                force_redraw = true;
+                            }
+                            KeyCode::Char('m') => {
+                                last_msg = Some(if bill_state.aggregate.is_empty() {
+                                    "请先按a完成分析".to_string()
+                                } else if bill_state.find_pairs() == 0 {
+                                    "未发现跨平台重复".to_string()
+                                } else {
+                                    format!("发现 {} 对疑似重复，逐条确认", bill_state.pairs.len())
+                                });
+                                force_redraw = true;
+                            }
+                            KeyCode::Char('c') => {
+                                last_msg = Some(if bill_state.aggregate.is_empty() {
+                                    "请先按a完成分析".to_string()
+                                } else {
+                                    match bill_state.reapply_rules() {
+                                        Ok(n) => format!("已重新分类，未分类支出 {} 笔", n),
+                                        Err(e) => e,
+                                    }
+                                });
+                                force_redraw = true;
+                            }
                             KeyCode::Char('o') => {
                                 if bill_state.aggregate.is_empty() {
                                     last_msg = Some("请先按a完成分析".into());
                                 } else {
                                     let default_dir = &bill_state.bill_dir;
                                     match prompt_export_directory(default_dir) {
-                                        Ok(dir) => match bill_state.export_reports(&dir) {
-                                            Ok(count) => {
-                                                last_msg = Some(format!(
-                                                    "已导出 {} 份报表至 {}",
-                                                    count,
-                                                    dir.display()
-                                                ));
+                                        Ok(dir) => match prompt_export_options() {
+                                            Ok(options) => {
+                                                match bill_state.export_reports(&dir, &options) {
+                                                    Ok(summary) if summary.files == 0 => {
+                                                        last_msg =
+                                                            Some("所选日期范围内没有交易".into())
+                                                    }
+                                                    Ok(summary) => {
+                                                        let mut msg = format!(
+                                                            "已导出 {} 份报表至 {}",
+                                                            summary.files,
+                                                            dir.display()
+                                                        );
+                                                        if options.formats.iter().any(|f| {
+                                                            matches!(
+                                                                f,
+                                                                ExportFormat::Beancount
+                                                                    | ExportFormat::Hledger
+                                                            )
+                                                        }) {
+                                                            let _ = write!(
+                                                                msg,
+                                                                "，新增分录 {} 笔，跳过已记账 {} 笔",
+                                                                summary.journal_added,
+                                                                summary.journal_skipped
+                                                            );
+                                                        }
+                                                        last_msg = Some(msg);
+                                                    }
+                                                    Err(e) => {
+                                                        last_msg = Some(format!("导出失败: {}", e))
+                                                    }
+                                                }
                                             }
-                                            Err(e) => last_msg = Some(format!("导出失败: {}", e)),
+                                            Err(e) => last_msg = Some(e),
                                         },
                                         Err(e) => {
                                             last_msg = Some(format!("输入导出路径失败: {}", e))
@@ -1303,6 +6524,12 @@ fn run_app(
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cfg = load_config();
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("check-links") => return check_links_headless(&cfg),
+        Some("import-bookmarks") => return import_bookmarks_headless(&cfg, args.next()),
+        _ => {}
+    }
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
@@ -1318,3 +6545,188 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     .ok();
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    /// 本地 HTTP 桩：/ok 返回 200，/moved 跳转到 /ok，/slow 迟迟不响应，其余 404
+    fn spawn_stub() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind stub");
+        let base = format!("http://{}", listener.local_addr().expect("stub addr"));
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(&stream);
+                    let mut request = String::new();
+                    if reader.read_line(&mut request).is_err() {
+                        return;
+                    }
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                        line.clear();
+                    }
+                    let head = match path.as_str() {
+                        "/ok" => "200 OK".to_string(),
+                        "/moved" => "302 Found\r\nLocation: /ok".to_string(),
+                        "/slow" => {
+                            thread::sleep(Duration::from_secs(3));
+                            "200 OK".to_string()
+                        }
+                        _ => "404 Not Found".to_string(),
+                    };
+                    let _ = write!(
+                        &stream,
+                        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        head
+                    );
+                });
+            }
+        });
+        base
+    }
+
+    #[test]
+    fn check_links_classifies_stub_responses() {
+        let base = spawn_stub();
+        let urls: Vec<String> = ["/ok", "/missing", "/moved", "/slow"]
+            .iter()
+            .map(|p| format!("{}{}", base, p))
+            .collect();
+        let client = build_http_client(Duration::from_millis(500));
+        let statuses = check_links(&client, &urls, 4);
+        assert_eq!(statuses[0], LinkStatus::Ok);
+        assert_eq!(statuses[1], LinkStatus::Http(404));
+        assert_eq!(statuses[2], LinkStatus::Redirect(format!("{}/ok", base)));
+        assert_eq!(statuses[3], LinkStatus::Timeout);
+    }
+
+    fn table(csv: &str) -> BillTable {
+        BillTable {
+            rows: csv
+                .lines()
+                .map(|line| line.split(',').map(|c| c.trim().to_string()).collect())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn cmb_debit_takes_partner_from_counterparty_column() {
+        let table = table(
+            "记账日期,货币,交易金额,联机余额,交易摘要,对手信息\n\
+             2026-10-06,CNY,-18.00,8800.00,快捷支付,瑞幸咖啡",
+        );
+        let header = CmbParser.sniff(&table).expect("cmb header");
+        let entries = CmbParser.parse(&table, header);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].partner, "瑞幸咖啡");
+        assert_eq!(entries[0].product, "快捷支付");
+        assert_eq!(entries[0].flow, BillFlow::Expense);
+        assert_eq!(entries[0].amount, 18.0);
+    }
+
+    #[test]
+    fn pomodoro_time_goes_to_the_open_recurring_row() {
+        let path = std::env::temp_dir().join(format!("entry-todo-{}.md", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        fs::write(
+            &path,
+            "| 任务 | 状态 | 截止 | 重复 | 耗时 |\n\
+             |------|------|------|------|------|\n\
+             | 浇花 | [ ] | 2026-10-01 | every day | 10m |\n",
+        )
+        .expect("write todo");
+        complete_todo(&path, 2).expect("complete");
+        record_todo_time(&path, "浇花", Duration::from_secs(35 * 60)).expect("record");
+
+        let rows = parse_table(&fs::read_to_string(&path).expect("read todo"), 1);
+        let _ = fs::remove_file(&path);
+        let done: Vec<&Vec<String>> = rows.iter().filter(|r| r[1] == "[x]").collect();
+        let open: Vec<&Vec<String>> = rows.iter().filter(|r| r[1] == "[ ]").collect();
+        assert_eq!(done[0][4], "10m");
+        assert_eq!(open[0][4], "35m");
+    }
+
+    fn bill_entry(partner: &str, time: &str, amount: f64, flow: BillFlow) -> BillEntry {
+        BillEntry {
+            source: "支付宝".into(),
+            time: parse_datetime(time),
+            partner: partner.into(),
+            product: String::new(),
+            amount,
+            category: String::new(),
+            platform_category: String::new(),
+            payment_method: String::new(),
+            status: String::new(),
+            flow,
+            refunded: 0.0,
+            order_id: String::new(),
+            refund_of: String::new(),
+        }
+    }
+
+    #[test]
+    fn matched_refund_posts_against_the_original_expense_account() {
+        let mut expense = bill_entry("美团", "2026-10-01 12:00:00", 30.0, BillFlow::Expense);
+        expense.platform_category = "餐饮美食".into();
+        let mut refund = bill_entry("美团", "2026-10-02 12:00:00", 30.0, BillFlow::Refund);
+        refund.platform_category = "退款".into();
+        let mut aggregate = BillAggregate::from_entries(vec![expense, refund]);
+        aggregate.apply_rules(&[]);
+        assert_eq!(aggregate.match_refunds(), 0);
+
+        let mut accounts = AccountConfig::default();
+        accounts
+            .category
+            .insert("餐饮美食".into(), "Expenses:Food".into());
+        let (text, added, _) = journal_entries(
+            &aggregate,
+            &accounts,
+            ExportFormat::Beancount,
+            &HashSet::new(),
+        );
+        assert_eq!(added, 2);
+        assert_eq!(text.matches("Expenses:Food").count(), 2);
+        assert!(!text.contains("Expenses:Uncategorized"));
+    }
+
+    fn wechat_coffee() -> BillEntry {
+        let mut e = bill_entry("瑞幸咖啡", "2026-10-06 08:30:00", 18.0, BillFlow::Expense);
+        e.source = "微信".into();
+        e.order_id = "4200001".into();
+        e
+    }
+
+    fn assert_pairs_with_wechat(bank: Vec<BillEntry>, bank_source: &str) {
+        assert_eq!(bank.len(), 1);
+        let aggregate = BillAggregate::from_entries(vec![wechat_coffee(), bank[0].clone()]);
+        let pairs = aggregate.find_cross_pairs(ChronoDuration::hours(48), &HashSet::new());
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].keep.source, "微信");
+        assert_eq!(pairs[0].drop.source, bank_source);
+    }
+
+    #[test]
+    fn wechat_expense_pairs_with_cmb_debit_row() {
+        let table = table(
+            "记账日期,货币,交易金额,联机余额,交易摘要,对手信息\n\
+             2026-10-06,CNY,-18.00,8800.00,快捷支付,瑞幸咖啡",
+        );
+        let header = CmbParser.sniff(&table).expect("cmb header");
+        assert_pairs_with_wechat(CmbParser.parse(&table, header), "招商银行");
+    }
+
+    #[test]
+    fn wechat_expense_pairs_with_icbc_debit_row() {
+        let table = table(
+            "交易日期,摘要,交易场所,交易国家或地区简称,钞/汇,交易金额(收入),交易金额(支出),\
+             交易币种,记账金额(收入),记账金额(支出),记账币种,余额,对方户名\n\
+             2026-10-06,消费,财付通-瑞幸咖啡,CHN,钞,,18.00,人民币,,18.00,人民币,982.00,",
+        );
+        let header = IcbcParser.sniff(&table).expect("icbc header");
+        assert_pairs_with_wechat(IcbcParser.parse(&table, header), "工商银行");
+    }
+}